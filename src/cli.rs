@@ -0,0 +1,153 @@
+use crate::pattern_io::ImagePalette;
+use crate::recorder::AnimationFormat;
+use crate::screenshot::MAX_SCREENSHOT_SIZE;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: wgpu-game-of-life [options]
+
+Options:
+  --session PATH         Restore saved session (.golsession) on startup
+  --headless             Run the simulation without a window
+  --size WxH             Grid size for headless mode (default 2000x2000, ignored with --session)
+  --pattern PATH         Pattern placed at the grid center in headless mode (.rle, .cells, .lif, .mc, .png)
+  --generations N        Generations to simulate in headless mode (default 100)
+  --save-session PATH    Save session after headless simulation finishes
+  --record PATH          Record animation, format is picked by extension (.gif, .png/.apng)
+  --region X,Y,W,H       Recorded region in cells (default: whole grid)
+  --every K              Capture a frame every K generations (default 1)
+  --scale S              Each cell becomes SxS pixels (default 1)
+  --delay MS             Delay between frames in milliseconds (default 100)
+  --palette mono|screen  Colors of recorded frames (default mono)
+  --theme NAME           Theme used by the screen palette and screenshots (default Classic), built-in or from themes.txt
+  --screenshot PATH      Save a PNG of the whole grid after headless simulation finishes
+  --screenshot-size WxH  Screenshot size in pixels (default 3840x2160)
+  --help                 Show this message";
+
+pub struct RecordOptions {
+    pub path: PathBuf,
+    pub format: AnimationFormat,
+    /// None means whole grid
+    pub region: Option<(u32, u32, u32, u32)>,
+    pub every_generations: u32,
+    pub scale: u32,
+    pub frame_delay_ms: u32,
+    pub palette: ImagePalette,
+}
+
+pub struct CliOptions {
+    pub headless: bool,
+    pub help: bool,
+    pub session: Option<PathBuf>,
+    pub size: (u32, u32),
+    pub pattern: Option<PathBuf>,
+    pub generations: u64,
+    pub save_session: Option<PathBuf>,
+    pub record: Option<RecordOptions>,
+    pub theme: String,
+    pub screenshot: Option<PathBuf>,
+    pub screenshot_size: (u32, u32),
+}
+
+impl CliOptions {
+    /// Parses arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            headless: false,
+            help: false,
+            session: None,
+            size: (2000, 2000),
+            pattern: None,
+            generations: 100,
+            save_session: None,
+            record: None,
+            theme: "Classic".to_string(),
+            screenshot: None,
+            screenshot_size: (3840, 2160),
+        };
+        let mut record_path = None;
+        let mut region = None;
+        let mut every_generations = 1;
+        let mut scale = 1;
+        let mut frame_delay_ms = 100;
+        let mut palette = ImagePalette::Monochrome;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--help" | "-h" => options.help = true,
+                "--session" => options.session = Some(PathBuf::from(value()?)),
+                "--size" => {
+                    let [width, height] = parse_numbers(&value()?, 'x')?;
+                    options.size = (width, height);
+                }
+                "--pattern" => options.pattern = Some(PathBuf::from(value()?)),
+                "--generations" => options.generations = parse_number(&value()?)?,
+                "--save-session" => options.save_session = Some(PathBuf::from(value()?)),
+                "--record" => record_path = Some(PathBuf::from(value()?)),
+                "--region" => {
+                    let [x, y, width, height] = parse_numbers(&value()?, ',')?;
+                    region = Some((x, y, width, height));
+                }
+                "--every" => every_generations = parse_number(&value()?)?,
+                "--scale" => scale = parse_number(&value()?)?,
+                "--delay" => frame_delay_ms = parse_number(&value()?)?,
+                "--palette" => {
+                    palette = match value()?.as_str() {
+                        "mono" => ImagePalette::Monochrome,
+                        "screen" => ImagePalette::Renderer,
+                        other => return Err(format!("unknown palette \"{}\"", other)),
+                    }
+                }
+                "--theme" => options.theme = value()?,
+                "--screenshot" => options.screenshot = Some(PathBuf::from(value()?)),
+                "--screenshot-size" => {
+                    let [width, height] = parse_numbers(&value()?, 'x')?;
+                    options.screenshot_size = (width, height);
+                }
+                _ => return Err(format!("unknown argument \"{}\"", arg)),
+            }
+        }
+
+        if options.size.0 == 0 || options.size.1 == 0 || every_generations == 0 || scale == 0 {
+            return Err("--size, --every and --scale must be greater than 0".to_string());
+        }
+        let (screenshot_width, screenshot_height) = options.screenshot_size;
+        if !(1..=MAX_SCREENSHOT_SIZE).contains(&screenshot_width) || !(1..=MAX_SCREENSHOT_SIZE).contains(&screenshot_height) {
+            return Err(format!("--screenshot-size must be between 1 and {} per side", MAX_SCREENSHOT_SIZE));
+        }
+        if let Some(path) = record_path {
+            let extension = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+            let format = match extension.as_deref() {
+                Some("gif") => AnimationFormat::Gif,
+                Some("png" | "apng") => AnimationFormat::Apng,
+                _ => return Err("--record path must end with .gif, .png or .apng".to_string()),
+            };
+            options.record = Some(RecordOptions {
+                path,
+                format,
+                region,
+                every_generations,
+                scale,
+                frame_delay_ms,
+                palette,
+            });
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid number \"{}\"", value))
+}
+
+fn parse_numbers<const N: usize>(value: &str, separator: char) -> Result<[u32; N], String> {
+    let numbers = value
+        .split(separator)
+        .map(parse_number)
+        .collect::<Result<Vec<u32>, String>>()?;
+    numbers
+        .try_into()
+        .map_err(|_| format!("expected {} numbers separated by '{}', got \"{}\"", N, separator, value))
+}
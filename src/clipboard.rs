@@ -0,0 +1,41 @@
+use crate::gol_manager::patterns::Pattern;
+use crate::pattern_io::{self, PatternFormat};
+use std::path::Path;
+
+/// Copied cells are kept here and also put to the system clipboard as RLE text, so they can be pasted into other Life programs.
+/// Paste prefers the system clipboard, so patterns copied from web pages or Golly work too
+pub struct Clipboard {
+    pattern: Option<Pattern>,
+    /// None when there is no clipboard available, e.g. no display server
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let system = arboard::Clipboard::new()
+            .inspect_err(|err| log::warn!("System clipboard is not available: {}", err))
+            .ok();
+        Self { pattern: None, system }
+    }
+
+    /// Internal copy always succeeds, returned error is only about the system clipboard
+    pub fn copy(&mut self, pattern: Pattern) -> Result<(), String> {
+        let text = pattern_io::write_pattern(&pattern, PatternFormat::Rle);
+        self.pattern = Some(pattern);
+        match &mut self.system {
+            Some(system) => system.set_text(text).map_err(|err| err.to_string()),
+            None => Err("system clipboard is not available".to_string()),
+        }
+    }
+
+    /// Pattern from the system clipboard if it holds a pattern in any text format, otherwise the last copied one.
+    /// See pattern_io::parse_pattern() for max_size
+    pub fn paste(&mut self, max_size: (u32, u32)) -> Option<Pattern> {
+        let text = self.system.as_mut().and_then(|system| system.get_text().ok());
+        let system_pattern = text.and_then(|text| {
+            let format = PatternFormat::detect(Path::new(""), &text)?;
+            pattern_io::parse_pattern(&text, format, max_size).ok()
+        });
+        system_pattern.or_else(|| self.pattern.clone())
+    }
+}
@@ -0,0 +1,93 @@
+use rand::Rng;
+
+pub const MAX_BRUSH_SIZE: u32 = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+    /// Random cells inside a circle, see Brush::density
+    Spray,
+}
+
+impl BrushShape {
+    pub const ALL: [BrushShape; 3] = [BrushShape::Square, BrushShape::Circle, BrushShape::Spray];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrushShape::Square => "Square",
+            BrushShape::Circle => "Circle",
+            BrushShape::Spray => "Spray",
+        }
+    }
+}
+
+pub struct Brush {
+    pub shape: BrushShape,
+    /// Width of the brush in cells, 1 is a single cell for every shape
+    pub size: u32,
+    /// 0 to 1, share of cells spray fills each time it's applied
+    pub density: f32,
+}
+
+impl Brush {
+    /// Cells covered by the brush centered at (x, y)
+    pub fn cells(&self, x: i64, y: i64, rng: &mut impl Rng) -> Vec<(i64, i64)> {
+        let size = self.size.clamp(1, MAX_BRUSH_SIZE) as i64;
+        // for even sizes the extra row/column goes to the bottom right
+        let start = -(size - 1) / 2;
+        let center = (size - 1) as f32 / 2.0 + start as f32;
+        // slightly smaller than half of the size, otherwise small circles are just squares
+        let radius_squared = (size as f32 / 2.0 - 0.25).powi(2);
+        let mut cells = Vec::with_capacity((size * size) as usize);
+        for dy in start..start + size {
+            for dx in start..start + size {
+                let inside_circle = (dx as f32 - center).powi(2) + (dy as f32 - center).powi(2) <= radius_squared;
+                let is_covered = match self.shape {
+                    BrushShape::Square => true,
+                    BrushShape::Circle => inside_circle,
+                    BrushShape::Spray => inside_circle && rng.gen::<f32>() < self.density,
+                };
+                if is_covered {
+                    cells.push((x + dx, y + dy));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Distance in cells between brush applications along the stroke.
+    /// Solid brushes are applied at every cell so the line has no gaps, spray would fill everything that way
+    pub fn spacing(&self) -> usize {
+        match self.shape {
+            BrushShape::Square | BrushShape::Circle => 1,
+            BrushShape::Spray => (self.size as usize / 2).max(1),
+        }
+    }
+}
+
+/// Cells of the line from start to end, both included
+pub fn bresenham_line(start: (i64, i64), end: (i64, i64)) -> Vec<(i64, i64)> {
+    let (mut x, mut y) = start;
+    let dx = (end.0 - x).abs();
+    let dy = -(end.1 - y).abs();
+    let step_x = if x < end.0 { 1 } else { -1 };
+    let step_y = if y < end.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut cells = Vec::with_capacity((dx - dy + 1) as usize);
+    loop {
+        cells.push((x, y));
+        if (x, y) == end {
+            return cells;
+        }
+        let error_doubled = 2 * error;
+        if error_doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if error_doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
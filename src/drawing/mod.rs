@@ -1,59 +1,627 @@
+mod brush;
+mod shape;
+mod symmetry;
+mod text;
+
+pub use brush::{Brush, BrushShape, MAX_BRUSH_SIZE};
+pub use shape::Shape;
+pub use symmetry::{Symmetry, SymmetryMode};
+pub use text::{render_text, TextFont, TextOptions, MAX_TEXT_SCALE};
+
+use brush::bresenham_line;
 use glam::{vec2, Mat3, Vec2};
-use winit::event::{MouseButton, WindowEvent};
-use std::sync::Arc;
+use rand::rngs::ThreadRng;
+use std::collections::HashSet;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::dpi::PhysicalSize;
 use egui_wgpu::wgpu;
 use crate::gol::GoL;
+use crate::history::History;
+use crate::gol_manager::patterns::Pattern;
 use crate::gol_renderer::GoLRenderer;
 
+/// How armed pattern is combined with cells already in the grid
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StampMode {
+    /// Alive cells of the pattern are added, nothing is removed
+    Or,
+    /// Alive cells of the pattern toggle grid cells, stamping twice restores the grid
+    Xor,
+    /// Whole pattern rectangle is copied, dead cells of the pattern clear the grid
+    Replace,
+}
+
+impl StampMode {
+    pub const ALL: [StampMode; 3] = [StampMode::Or, StampMode::Xor, StampMode::Replace];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StampMode::Or => "OR",
+            StampMode::Xor => "XOR",
+            StampMode::Replace => "Replace",
+        }
+    }
+}
+
+/// What left mouse button does when no pattern is armed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Brush,
+    /// Drag selects a rectangle for copy, cut, clear, invert and fill
+    Select,
+    /// Click flips the connected region of dead or alive cells
+    Fill,
+    /// Drag shows a preview, the shape is drawn when the button is released
+    Shape(Shape),
+}
+
+impl Tool {
+    pub const ALL: [Tool; 7] = [
+        Tool::Brush,
+        Tool::Select,
+        Tool::Fill,
+        Tool::Shape(Shape::Line),
+        Tool::Shape(Shape::Rectangle),
+        Tool::Shape(Shape::FilledRectangle),
+        Tool::Shape(Shape::Ellipse),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Brush => "Brush",
+            Tool::Select => "Select",
+            Tool::Fill => "Fill",
+            Tool::Shape(shape) => shape.name(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only cells sharing an edge are connected
+    Four,
+    /// Diagonal neighbors are connected too
+    Eight,
+}
+
+impl Connectivity {
+    fn neighbors(&self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            Connectivity::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)],
+        }
+    }
+}
+
+/// Corner cells of the selected rectangle, both included, in the order they were picked
+#[derive(Clone, Copy)]
+struct Selection {
+    start: (i64, i64),
+    end: (i64, i64),
+}
+
+/// Cells painted while a mouse button is held. They are uploaded once per frame, see flush_stroke()
+struct Stroke {
+    /// 1 when drawing with the left button, 0 when erasing with the right one
+    value: u8,
+    last_cell: (i64, i64),
+    /// Cells moved along the line since the brush was last applied, see Brush::spacing()
+    distance: usize,
+    /// Only cells with a symmetric copy in the grid are kept, see Symmetry::get_source_bounds()
+    bounds: (i64, i64, i64, i64),
+    pending_cells: HashSet<(i64, i64)>,
+}
+
+impl Stroke {
+    /// Brush applied far outside of the grid is skipped without generating its cells
+    fn add_brush_cells(&mut self, brush: &Brush, (x, y): (i64, i64), rng: &mut ThreadRng) {
+        let (min_x, min_y, max_x, max_y) = self.bounds;
+        let reach = MAX_BRUSH_SIZE as i64;
+        if x < min_x - reach || y < min_y - reach || x > max_x + reach || y > max_y + reach {
+            return;
+        }
+        let cells = brush.cells(x, y, rng).into_iter();
+        self.pending_cells
+            .extend(cells.filter(|(x, y)| *x >= min_x && *y >= min_y && *x <= max_x && *y <= max_y));
+    }
+}
+
+/// Shape being dragged, see Tool::Shape
+struct ShapeDrag {
+    shape: Shape,
+    /// Same as Stroke::value
+    value: u8,
+    start: (i64, i64),
+    /// Cells of the shape relative to top_left, shown as ghost until the button is released
+    preview: Pattern,
+    top_left: (i64, i64),
+}
+
 pub struct GoLDrawing {
     mouse_position: Option<Vec2>,
+    /// Placed with left click, centered on the cursor. Stays armed until right click or Escape, so it can be stamped many times
+    armed_pattern: Option<Pattern>,
+    /// Set when armed pattern or shape preview changes, so renderer can upload the new ghost
+    preview_changed: bool,
+    pub stamp_mode: StampMode,
+    pub tool: Tool,
+    pub brush: Brush,
+    /// Strokes are copied to all symmetric positions
+    pub symmetry: Symmetry,
+    /// Next left click moves the symmetry center instead of drawing
+    pub is_picking_symmetry_center: bool,
+    pub fill_connectivity: Connectivity,
+    /// Fill is canceled when the region has more cells, e.g. when clicking an empty grid by accident
+    pub max_fill_cells: u32,
+    stroke: Option<Stroke>,
+    shape_drag: Option<ShapeDrag>,
+    selection: Option<Selection>,
+    /// Selection end follows the cursor while the button is held
+    is_selecting: bool,
+    rng: ThreadRng,
+    /// Message for the user, e.g. why fill was canceled
+    status: Option<String>,
 }
 
 impl GoLDrawing {
     pub fn new() -> Self {
         Self {
             mouse_position: None,
+            armed_pattern: None,
+            preview_changed: false,
+            stamp_mode: StampMode::Or,
+            brush: Brush {
+                shape: BrushShape::Square,
+                size: 1,
+                density: 0.3,
+            },
+            tool: Tool::Brush,
+            symmetry: Symmetry {
+                mode: SymmetryMode::None,
+                center: Vec2::ZERO,
+            },
+            is_picking_symmetry_center: false,
+            fill_connectivity: Connectivity::Four,
+            max_fill_cells: 100_000,
+            stroke: None,
+            shape_drag: None,
+            selection: None,
+            is_selecting: false,
+            rng: rand::thread_rng(),
+            status: None,
         }
     }
+    pub fn arm(&mut self, pattern: Pattern) {
+        self.armed_pattern = Some(pattern);
+        self.preview_changed = true;
+    }
+    pub fn disarm(&mut self) {
+        self.armed_pattern = None;
+        self.preview_changed = true;
+    }
+    pub fn get_armed_pattern(&self) -> Option<&Pattern> {
+        self.armed_pattern.as_ref()
+    }
+    /// Returns true once after the preview pattern changed
+    pub fn take_preview_changed(&mut self) -> bool {
+        std::mem::take(&mut self.preview_changed)
+    }
+    pub fn take_status(&mut self) -> Option<String> {
+        self.status.take()
+    }
+
+    /// Pattern shown as ghost: the dragged shape, otherwise the armed pattern
+    pub fn get_preview_pattern(&self) -> Option<&Pattern> {
+        match &self.shape_drag {
+            Some(shape_drag) => Some(&shape_drag.preview),
+            None => self.armed_pattern.as_ref(),
+        }
+    }
+    /// Top left cell of get_preview_pattern()
+    pub fn get_preview_position(
+        &self,
+        screen_size: PhysicalSize<u32>,
+        gol: &GoL,
+        gol_view_proj: Mat3,
+        gol_quad_transform: Mat3,
+    ) -> Option<(i64, i64)> {
+        match &self.shape_drag {
+            Some(shape_drag) => Some(shape_drag.top_left),
+            None => self.get_stamp_position(screen_size, gol, gol_view_proj, gol_quad_transform),
+        }
+    }
+
+    /// Selected area clipped to the grid as (x, y, width, height)
+    pub fn get_selection(&self, gol: &GoL) -> Option<(u32, u32, u32, u32)> {
+        let selection = self.selection?;
+        let x = selection.start.0.min(selection.end.0);
+        let y = selection.start.1.min(selection.end.1);
+        let width = (selection.start.0 - selection.end.0).unsigned_abs() + 1;
+        let height = (selection.start.1 - selection.end.1).unsigned_abs() + 1;
+        gol.clip_area(x, y, width.min(u32::MAX as u64) as u32, height.min(u32::MAX as u64) as u32)
+    }
+    pub fn select_area(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.selection = Some(Selection {
+            start: (x as i64, y as i64),
+            end: (x as i64 + width as i64 - 1, y as i64 + height as i64 - 1),
+        });
+    }
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+        self.is_selecting = false;
+    }
+
+    /// Top left cell where armed pattern would be stamped now, None when nothing is armed or cursor is outside the view
+    pub fn get_stamp_position(
+        &self,
+        screen_size: PhysicalSize<u32>,
+        gol: &GoL,
+        gol_view_proj: Mat3,
+        gol_quad_transform: Mat3,
+    ) -> Option<(i64, i64)> {
+        let pattern = self.armed_pattern.as_ref()?;
+        let (cell_x, cell_y) = self.get_cursor_cell(screen_size, gol, gol_view_proj, gol_quad_transform)?;
+        Some((cell_x - pattern.width as i64 / 2, cell_y - pattern.height as i64 / 2))
+    }
+
+    /// Cell under the cursor, can be outside of the grid
+    fn get_cursor_cell(
+        &self,
+        screen_size: PhysicalSize<u32>,
+        gol: &GoL,
+        gol_view_proj: Mat3,
+        gol_quad_transform: Mat3,
+    ) -> Option<(i64, i64)> {
+        let cell = self.get_cursor_point(screen_size, gol, gol_view_proj, gol_quad_transform)?.floor();
+        Some((cell.x as i64, cell.y as i64))
+    }
+
+    /// Position of the cursor in cells, not rounded
+    fn get_cursor_point(
+        &self,
+        screen_size: PhysicalSize<u32>,
+        gol: &GoL,
+        gol_view_proj: Mat3,
+        gol_quad_transform: Mat3,
+    ) -> Option<Vec2> {
+        let mouse_position = self.mouse_position?;
+        let mut ndc = mouse_position / vec2(screen_size.width.max(1) as f32, screen_size.height.max(1) as f32) * 2.0 - vec2(1.0, 1.0);
+        ndc.y = -ndc.y;
+        let uv = GoLRenderer::ndc_to_gol_uv(ndc, gol_view_proj, gol_quad_transform);
+        Some(uv * vec2(gol.get_size().0 as f32, gol.get_size().1 as f32))
+    }
+
+    /// Only the part of the pattern inside the grid is written
+    #[allow(clippy::too_many_arguments)]
+    fn stamp(&self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL, history: &mut History, pattern: &Pattern, x: i64, y: i64) {
+        let Some((area_x, area_y, width, height)) = gol.clip_area(x, y, pattern.width, pattern.height) else {
+            return;
+        };
+        let stamp_mode = self.stamp_mode;
+        history.edit_area(gol, device, queue, area_x, area_y, width, height, |cells| {
+            for row in 0..height {
+                let pattern_row = (area_y as i64 + row as i64 - y) as usize;
+                for column in 0..width {
+                    let pattern_column = (area_x as i64 + column as i64 - x) as usize;
+                    let value = pattern.data[pattern_row * pattern.width as usize + pattern_column];
+                    let cell = &mut cells[(row * width + column) as usize];
+                    *cell = match stamp_mode {
+                        StampMode::Or => *cell | value,
+                        StampMode::Xor => *cell ^ value,
+                        StampMode::Replace => value,
+                    };
+                }
+            }
+        });
+    }
+
+    fn start_stroke(&mut self, value: u8, cell: (i64, i64), gol: &GoL) {
+        let mut stroke = Stroke {
+            value,
+            last_cell: cell,
+            distance: 0,
+            bounds: self.symmetry.get_source_bounds(gol.get_size()),
+            pending_cells: HashSet::new(),
+        };
+        stroke.add_brush_cells(&self.brush, cell, &mut self.rng);
+        self.stroke = Some(stroke);
+    }
+
+    /// Cursor moves many cells between events when it's fast, the gap is filled with a line
+    fn continue_stroke(&mut self, cell: (i64, i64)) {
+        let Some(stroke) = &mut self.stroke else {
+            return;
+        };
+        if cell == stroke.last_cell {
+            return;
+        }
+        let spacing = self.brush.spacing();
+        for (x, y) in bresenham_line(stroke.last_cell, cell).into_iter().skip(1) {
+            stroke.distance += 1;
+            if stroke.distance >= spacing {
+                stroke.distance = 0;
+                stroke.add_brush_cells(&self.brush, (x, y), &mut self.rng);
+            }
+        }
+        stroke.last_cell = cell;
+    }
+
+    /// Uploads cells painted since the last call, without waiting for the GPU (see History::paint_cells()).
+    /// Each symmetric copy gets its own readback box, one box around all of them would often be the whole grid
+    pub fn flush_stroke(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL, history: &mut History) {
+        let Some(stroke) = &mut self.stroke else {
+            return;
+        };
+        if stroke.pending_cells.is_empty() {
+            return;
+        }
+        let (gol_width, gol_height) = gol.get_size();
+        let mut copies = vec![Vec::with_capacity(stroke.pending_cells.len()); self.symmetry.image_count()];
+        for cell in stroke.pending_cells.drain() {
+            for (copy, image) in copies.iter_mut().zip(self.symmetry.images(cell)) {
+                copy.push(image);
+            }
+        }
+        for cells in copies {
+            let cells: Vec<(u32, u32)> = cells
+                .into_iter()
+                .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < gol_width as i64 && *y < gol_height as i64)
+                .map(|(x, y)| (x as u32, y as u32))
+                .collect();
+            history.paint_cells(gol, device, queue, &cells, stroke.value);
+        }
+    }
+
+    fn start_shape(&mut self, shape: Shape, value: u8, cell: (i64, i64), gol: &GoL) {
+        let mut shape_drag = ShapeDrag {
+            shape,
+            value,
+            start: cell,
+            preview: Pattern::new(Vec::new(), 0, 0),
+            top_left: cell,
+        };
+        Self::update_shape_preview(&mut shape_drag, cell, gol.get_size());
+        self.shape_drag = Some(shape_drag);
+        self.preview_changed = true;
+    }
+
+    /// Both corners are clamped to the grid, so the preview is never bigger than the grid (and its ghost texture)
+    fn update_shape_preview(shape_drag: &mut ShapeDrag, end: (i64, i64), (gol_width, gol_height): (u32, u32)) {
+        let clamp = |(x, y): (i64, i64)| (x.clamp(0, gol_width as i64 - 1), y.clamp(0, gol_height as i64 - 1));
+        let (start, end) = (clamp(shape_drag.start), clamp(end));
+        let top_left = (start.0.min(end.0), start.1.min(end.1));
+        let width = (start.0 - end.0).unsigned_abs() as u32 + 1;
+        let height = (start.1 - end.1).unsigned_abs() as u32 + 1;
+        let mut data = vec![0; width as usize * height as usize];
+        for (x, y) in shape_drag.shape.cells(start, end) {
+            data[((y - top_left.1) * width as i64 + x - top_left.0) as usize] = 1;
+        }
+        shape_drag.preview = Pattern::new(data, width, height);
+        shape_drag.top_left = top_left;
+    }
+
+    /// Writes the dragged shape with a single write_area() of its bounding box
+    fn finish_shape(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL, history: &mut History) {
+        let Some(shape_drag) = self.shape_drag.take() else {
+            return;
+        };
+        self.preview_changed = true;
+        let preview = &shape_drag.preview;
+        let (x, y) = shape_drag.top_left;
+        let Some((area_x, area_y, width, height)) = gol.clip_area(x, y, preview.width, preview.height) else {
+            return;
+        };
+        history.edit_area(gol, device, queue, area_x, area_y, width, height, |cells| {
+            for row in 0..height {
+                let preview_row = (area_y as i64 + row as i64 - y) as usize;
+                for column in 0..width {
+                    let preview_column = (area_x as i64 + column as i64 - x) as usize;
+                    if preview.data[preview_row * preview.width as usize + preview_column] == 1 {
+                        cells[(row * width + column) as usize] = shape_drag.value;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Flips the connected region of cells with the same state as the clicked one. Region of max_fill_cells can't
+    /// reach further than that from the click, so only that box is read. Only the bounding box of the region is written
+    fn flood_fill(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL, history: &mut History, cell: (i64, i64)) {
+        let (gol_width, gol_height) = gol.get_size();
+        if cell.0 < 0 || cell.1 < 0 || cell.0 >= gol_width as i64 || cell.1 >= gol_height as i64 {
+            return;
+        }
+        let radius = self.max_fill_cells as i64;
+        let side = (2 * radius + 1).min(u32::MAX as i64) as u32;
+        let Some((box_x, box_y, box_width, box_height)) = gol.clip_area(cell.0 - radius, cell.1 - radius, side, side) else {
+            return;
+        };
+        let (box_x, box_y) = (box_x as i64, box_y as i64);
+        let mut grid = pollster::block_on(gol.read_area(device, queue, box_x as u32, box_y as u32, box_width, box_height));
+        let index = |(x, y): (i64, i64)| (y - box_y) as usize * box_width as usize + (x - box_x) as usize;
+        let old_value = grid[index(cell)];
+        // other states of custom rules become dead
+        let new_value = (old_value == 0) as u8;
+        grid[index(cell)] = new_value;
+        let mut filled = vec![cell];
+        let mut stack = vec![cell];
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in self.fill_connectivity.neighbors() {
+                let neighbor = (x + dx, y + dy);
+                if neighbor.0 < 0 || neighbor.1 < 0 || neighbor.0 >= gol_width as i64 || neighbor.1 >= gol_height as i64 {
+                    continue;
+                }
+                let is_outside_box = neighbor.0 < box_x
+                    || neighbor.1 < box_y
+                    || neighbor.0 >= box_x + box_width as i64
+                    || neighbor.1 >= box_y + box_height as i64;
+                if !is_outside_box && grid[index(neighbor)] != old_value {
+                    continue;
+                }
+                if is_outside_box || filled.len() >= self.max_fill_cells as usize {
+                    self.status = Some(format!("Fill canceled, region has more than {} cells", self.max_fill_cells));
+                    return;
+                }
+                grid[index(neighbor)] = new_value;
+                filled.push(neighbor);
+                stack.push(neighbor);
+            }
+        }
+
+        let min_x = filled.iter().map(|(x, _)| *x).min().unwrap();
+        let min_y = filled.iter().map(|(_, y)| *y).min().unwrap();
+        let width = filled.iter().map(|(x, _)| *x).max().unwrap() - min_x + 1;
+        let height = filled.iter().map(|(_, y)| *y).max().unwrap() - min_y + 1;
+        history.edit_area(gol, device, queue, min_x as u32, min_y as u32, width as u32, height as u32, |area| {
+            for (x, y) in &filled {
+                area[((y - min_y) * width + x - min_x) as usize] = new_value;
+            }
+        });
+        self.status = Some(format!("Filled {} cells", filled.len()));
+    }
+
+    /// R rotates armed pattern clockwise, F flips it horizontally and V vertically
+    fn transform_armed_pattern(&mut self, keycode: KeyCode) -> bool {
+        let Some(pattern) = &self.armed_pattern else {
+            return false;
+        };
+        let transformed = match keycode {
+            KeyCode::KeyR => pattern.rotated_clockwise(),
+            KeyCode::KeyF => pattern.flipped_horizontally(),
+            KeyCode::KeyV => pattern.flipped_vertically(),
+            _ => return false,
+        };
+        self.arm(transformed);
+        true
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn handle_input(
         &mut self,
         event: &WindowEvent,
-        window: Arc<winit::window::Window>,
+        screen_size: PhysicalSize<u32>,
         gol: &GoL,
         gol_view_proj: Mat3,
         gol_quad_transform: Mat3,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
+        history: &mut History,
     ) -> bool {
         match event {
-            WindowEvent::MouseInput { button, state, .. } => {
-                if state.is_pressed() {
-                    if let Some(mouse_position) = self.mouse_position {
-                        let mut ndc = mouse_position / vec2(window.inner_size().width as f32, window.inner_size().height as f32) * 2.0 - vec2(1.0, 1.0);
-                        ndc.y = -ndc.y;
-                        let uv = GoLRenderer::ndc_to_gol_uv(ndc, gol_view_proj, gol_quad_transform);
-                        if uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 {
-                            return true;
+            WindowEvent::MouseInput { button, state, .. } if state.is_pressed() && self.is_picking_symmetry_center => {
+                if *button == MouseButton::Left {
+                    let Some(point) = self.get_cursor_point(screen_size, gol, gol_view_proj, gol_quad_transform) else {
+                        return false;
+                    };
+                    // nearest cell corner or cell center
+                    self.symmetry.center = (point * 2.0).round() / 2.0;
+                }
+                self.is_picking_symmetry_center = false;
+                return true;
+            }
+            WindowEvent::MouseInput { button, state, .. } if state.is_pressed() => {
+                if let Some(pattern) = &self.armed_pattern {
+                    match button {
+                        MouseButton::Left => {
+                            if let Some((x, y)) = self.get_stamp_position(screen_size, gol, gol_view_proj, gol_quad_transform) {
+                                self.stamp(device, queue, gol, history, pattern, x, y);
+                            }
                         }
-                        let pixel_gol_position = uv * vec2(gol.get_size().0 as f32, gol.get_size().1 as f32);
-
-                        let new_value = if *button == MouseButton::Left {
-                            1
-                        } else if *button == MouseButton::Right {
-                            0
-                        } else {
-                            return false;
-                        };
-                        gol.write_area(queue, &[new_value], pixel_gol_position.x as u32, pixel_gol_position.y as u32, 1, 1);
-                        return true;
+                        MouseButton::Right => self.disarm(),
+                        _ => return false,
                     }
+                    return true;
                 }
+                let Some(cell) = self.get_cursor_cell(screen_size, gol, gol_view_proj, gol_quad_transform) else {
+                    return false;
+                };
+                match (self.tool, button) {
+                    (Tool::Select, MouseButton::Left) => {
+                        self.selection = Some(Selection { start: cell, end: cell });
+                        self.is_selecting = true;
+                    }
+                    (Tool::Select, MouseButton::Right) => self.clear_selection(),
+                    (Tool::Brush, MouseButton::Left) => {
+                        history.begin_command();
+                        self.start_stroke(1, cell, gol);
+                    }
+                    (Tool::Brush, MouseButton::Right) => {
+                        history.begin_command();
+                        self.start_stroke(0, cell, gol);
+                    }
+                    (Tool::Fill, MouseButton::Left) => self.flood_fill(device, queue, gol, history, cell),
+                    (Tool::Shape(shape), MouseButton::Left) => self.start_shape(shape, 1, cell, gol),
+                    (Tool::Shape(shape), MouseButton::Right) => self.start_shape(shape, 0, cell, gol),
+                    _ => return false,
+                }
+                return true;
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, .. } if self.is_selecting => {
+                self.is_selecting = false;
+                return true;
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, .. } if self.stroke.is_some() => {
+                self.flush_stroke(device, queue, gol, history);
+                self.stroke = None;
+                history.end_command(device);
+                return true;
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, .. } if self.shape_drag.is_some() => {
+                self.finish_shape(device, queue, gol, history);
+                return true;
             }
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse_position = Some(vec2(position.x as f32, position.y as f32));
+                if let Some(cell) = self.get_cursor_cell(screen_size, gol, gol_view_proj, gol_quad_transform) {
+                    if self.stroke.is_some() {
+                        self.continue_stroke(cell);
+                    }
+                    if let Some(selection) = self.selection.as_mut().filter(|_| self.is_selecting) {
+                        selection.end = cell;
+                    }
+                    if let Some(shape_drag) = &mut self.shape_drag {
+                        Self::update_shape_preview(shape_drag, cell, gol.get_size());
+                        self.preview_changed = true;
+                    }
+                }
                 return false;
             }
+            WindowEvent::CursorLeft { .. } => {
+                self.mouse_position = None;
+                return false;
+            }
+            WindowEvent::KeyboardInput { event, .. }
+                if self.shape_drag.is_some()
+                    && event.state == ElementState::Pressed
+                    && event.physical_key == PhysicalKey::Code(KeyCode::Escape) =>
+            {
+                self.shape_drag = None;
+                self.preview_changed = true;
+                return true;
+            }
+            WindowEvent::KeyboardInput { event, .. }
+                if self.armed_pattern.is_none()
+                    && self.selection.is_some()
+                    && event.state == ElementState::Pressed
+                    && event.physical_key == PhysicalKey::Code(KeyCode::Escape) =>
+            {
+                self.clear_selection();
+                return true;
+            }
+            WindowEvent::KeyboardInput { event, .. } if self.armed_pattern.is_some() && event.state == ElementState::Pressed => {
+                let PhysicalKey::Code(keycode) = event.physical_key else {
+                    return false;
+                };
+                // Escape cancels placement instead of closing the app
+                if keycode == KeyCode::Escape {
+                    self.disarm();
+                    return true;
+                }
+                return self.transform_armed_pattern(keycode);
+            }
             _ => {}
         }
         false
     }
-}
\ No newline at end of file
+}
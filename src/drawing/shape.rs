@@ -0,0 +1,56 @@
+use super::brush::bresenham_line;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Line,
+    Rectangle,
+    FilledRectangle,
+    /// Outline of the ellipse inscribed in the dragged rectangle
+    Ellipse,
+}
+
+impl Shape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Line => "Line",
+            Shape::Rectangle => "Rectangle",
+            Shape::FilledRectangle => "Filled rectangle",
+            Shape::Ellipse => "Ellipse",
+        }
+    }
+
+    /// Cells of the shape dragged from start to end, both corners included
+    pub fn cells(&self, start: (i64, i64), end: (i64, i64)) -> Vec<(i64, i64)> {
+        let (min_x, max_x) = (start.0.min(end.0), start.0.max(end.0));
+        let (min_y, max_y) = (start.1.min(end.1), start.1.max(end.1));
+        match self {
+            Shape::Line => bresenham_line(start, end),
+            Shape::Rectangle => (min_y..=max_y)
+                .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+                .filter(|(x, y)| *x == min_x || *x == max_x || *y == min_y || *y == max_y)
+                .collect(),
+            Shape::FilledRectangle => (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| (x, y))).collect(),
+            Shape::Ellipse => {
+                let center_x = (min_x + max_x + 1) as f64 / 2.0;
+                let center_y = (min_y + max_y + 1) as f64 / 2.0;
+                let radius_x = (max_x - min_x + 1) as f64 / 2.0;
+                let radius_y = (max_y - min_y + 1) as f64 / 2.0;
+                let is_inside = |x: i64, y: i64| {
+                    let dx = (x as f64 + 0.5 - center_x) / radius_x;
+                    let dy = (y as f64 + 0.5 - center_y) / radius_y;
+                    dx * dx + dy * dy <= 1.0
+                };
+                // outline is inside cells next to an outside cell, so it has no gaps at any size
+                (min_y..=max_y)
+                    .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+                    .filter(|(x, y)| {
+                        is_inside(*x, *y)
+                            && [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                                .iter()
+                                .any(|(dx, dy)| !is_inside(x + dx, y + dy))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
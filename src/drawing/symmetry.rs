@@ -0,0 +1,139 @@
+use glam::{vec2, Vec2};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SymmetryMode {
+    None,
+    /// Mirrored left to right across the vertical axis
+    Horizontal,
+    /// Mirrored top to bottom across the horizontal axis
+    Vertical,
+    /// Both mirrors, 4 copies
+    BothAxes,
+    /// Rotated by 180 degrees
+    Rotational2,
+    /// Rotated by 90 degrees, 4 copies
+    Rotational4,
+    /// All rotations and mirrors of the square (D8 group), 8 copies
+    Dihedral8,
+}
+
+impl SymmetryMode {
+    pub const ALL: [SymmetryMode; 7] = [
+        SymmetryMode::None,
+        SymmetryMode::Horizontal,
+        SymmetryMode::Vertical,
+        SymmetryMode::BothAxes,
+        SymmetryMode::Rotational2,
+        SymmetryMode::Rotational4,
+        SymmetryMode::Dihedral8,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymmetryMode::None => "None",
+            SymmetryMode::Horizontal => "Horizontal",
+            SymmetryMode::Vertical => "Vertical",
+            SymmetryMode::BothAxes => "Both axes",
+            SymmetryMode::Rotational2 => "2-fold",
+            SymmetryMode::Rotational4 => "4-fold",
+            SymmetryMode::Dihedral8 => "8-fold (D8)",
+        }
+    }
+
+    fn is_rotating_by_90(&self) -> bool {
+        matches!(self, SymmetryMode::Rotational4 | SymmetryMode::Dihedral8)
+    }
+}
+
+pub struct Symmetry {
+    pub mode: SymmetryMode,
+    /// In cells, snapped to cell corners and cell centers when used, see get_center()
+    pub center: Vec2,
+}
+
+impl Symmetry {
+    /// Center the copies are made around. Rotating by 90 degrees maps cells onto cells only when
+    /// the center is a cell corner or a cell center, so for those modes y is moved by half a cell if needed
+    pub fn get_center(&self) -> Vec2 {
+        let (x, y) = self.get_doubled_center();
+        vec2(x as f32, y as f32) / 2.0
+    }
+
+    /// Center in half cells, so it's integer
+    fn get_doubled_center(&self) -> (i64, i64) {
+        let x = (self.center.x * 2.0).round() as i64;
+        let mut y = (self.center.y * 2.0).round() as i64;
+        if self.mode.is_rotating_by_90() && (x + y) % 2 != 0 {
+            y += 1;
+        }
+        (x, y)
+    }
+
+    /// Number of copies of every cell including the cell itself, images() returns them in the same order every time
+    pub fn image_count(&self) -> usize {
+        match self.mode {
+            SymmetryMode::None => 1,
+            SymmetryMode::Horizontal | SymmetryMode::Vertical | SymmetryMode::Rotational2 => 2,
+            SymmetryMode::BothAxes | SymmetryMode::Rotational4 => 4,
+            SymmetryMode::Dihedral8 => 8,
+        }
+    }
+
+    /// The cell and all its symmetric copies, the cell itself is first
+    pub fn images(&self, (x, y): (i64, i64)) -> Vec<(i64, i64)> {
+        // cell centers in half cells are odd, so everything stays integer
+        let (center_x, center_y) = self.get_doubled_center();
+        let (dx, dy) = (2 * x + 1 - center_x, 2 * y + 1 - center_y);
+        let offsets = match self.mode {
+            SymmetryMode::None => vec![(dx, dy)],
+            SymmetryMode::Horizontal => vec![(dx, dy), (-dx, dy)],
+            SymmetryMode::Vertical => vec![(dx, dy), (dx, -dy)],
+            SymmetryMode::BothAxes => vec![(dx, dy), (-dx, dy), (dx, -dy), (-dx, -dy)],
+            SymmetryMode::Rotational2 => vec![(dx, dy), (-dx, -dy)],
+            SymmetryMode::Rotational4 => vec![(dx, dy), (-dy, dx), (-dx, -dy), (dy, -dx)],
+            SymmetryMode::Dihedral8 => vec![
+                (dx, dy),
+                (-dy, dx),
+                (-dx, -dy),
+                (dy, -dx),
+                (-dx, dy),
+                (dx, -dy),
+                (dy, dx),
+                (-dy, -dx),
+            ],
+        };
+        offsets
+            .into_iter()
+            .map(|(dx, dy)| ((center_x + dx - 1).div_euclid(2), (center_y + dy - 1).div_euclid(2)))
+            .collect()
+    }
+
+    /// Bounding box (min x, min y, max x, max y, all included) of cells that have at least one copy inside the grid.
+    /// Copies of the grid corners under all the transforms span it, since every transform's inverse is one of them
+    pub fn get_source_bounds(&self, (width, height): (u32, u32)) -> (i64, i64, i64, i64) {
+        let (max_x, max_y) = (width as i64 - 1, height as i64 - 1);
+        let images: Vec<(i64, i64)> = [(0, 0), (max_x, 0), (0, max_y), (max_x, max_y)]
+            .into_iter()
+            .flat_map(|corner| self.images(corner))
+            .collect();
+        (
+            images.iter().map(|(x, _)| *x).min().unwrap(),
+            images.iter().map(|(_, y)| *y).min().unwrap(),
+            images.iter().map(|(x, _)| *x).max().unwrap(),
+            images.iter().map(|(_, y)| *y).max().unwrap(),
+        )
+    }
+
+    /// Mirror axes and lines the copies are rotated around, as (point, direction) in cells
+    pub fn guides(&self) -> Vec<(Vec2, Vec2)> {
+        let center = self.get_center();
+        let directions = match self.mode {
+            SymmetryMode::None => vec![],
+            SymmetryMode::Horizontal => vec![Vec2::Y],
+            SymmetryMode::Vertical => vec![Vec2::X],
+            SymmetryMode::BothAxes | SymmetryMode::Rotational2 | SymmetryMode::Rotational4 => vec![Vec2::X, Vec2::Y],
+            SymmetryMode::Dihedral8 => vec![Vec2::X, Vec2::Y, vec2(1.0, 1.0), vec2(1.0, -1.0)],
+        };
+        directions.into_iter().map(|direction| (center, direction.normalize())).collect()
+    }
+}
@@ -1,4 +1,78 @@
+use crate::shader_reload::create_checked;
 use egui_wgpu::wgpu;
+use std::cell::Cell;
+use std::sync::mpsc;
+
+/// Built-in rule, see GoL::set_shader() for custom ones
+pub const SHADER_SOURCE: &str = include_str!("shaders.wgsl");
+
+/// Readback started with GoL::start_area_read(). Data becomes available after the GPU finishes the copy,
+/// device has to be polled (wgpu::Maintain::Poll is enough) for that to happen.
+pub struct PendingRead {
+    buffer: wgpu::Buffer,
+    padded_width: u32,
+    width: u32,
+    receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl PendingRead {
+    /// Returns None while the copy is still in flight. Mapping fails e.g. when the device is lost
+    pub fn try_take(&self) -> Option<Result<Vec<u8>, String>> {
+        if let Err(err) = self.receiver.try_recv().ok()? {
+            return Some(Err(err.to_string()));
+        }
+        let data = self.buffer.slice(..).get_mapped_range();
+        let mut vec = Vec::with_capacity(data.len() / self.padded_width as usize * self.width as usize);
+        for row in data.chunks(self.padded_width as usize) {
+            vec.extend_from_slice(&row[..self.width as usize]);
+        }
+        Some(Ok(vec))
+    }
+}
+
+/// One bit per cell, 8 cells per byte. Any non-zero state is stored as 1, see PackedCells for cells that can have more states
+pub fn pack_cells(cells: &[u8]) -> Vec<u8> {
+    cells
+        .chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, cell)| byte | (((*cell != 0) as u8) << i)))
+        .collect()
+}
+
+pub fn unpack_cells(packed: &[u8], cell_count: usize) -> Vec<u8> {
+    (0..cell_count).map(|i| packed[i / 8] >> (i % 8) & 1).collect()
+}
+
+/// Cells kept in memory without losing states of custom rules. Bit-packed when all cells are 0 or 1 (the usual case),
+/// one byte per cell otherwise
+pub enum PackedCells {
+    Bits(Vec<u8>),
+    Bytes(Vec<u8>),
+}
+
+impl PackedCells {
+    pub fn pack(cells: &[u8]) -> Self {
+        if cells.iter().all(|cell| *cell <= 1) {
+            PackedCells::Bits(pack_cells(cells))
+        } else {
+            PackedCells::Bytes(cells.to_vec())
+        }
+    }
+
+    pub fn unpack(&self, cell_count: usize) -> Vec<u8> {
+        match self {
+            PackedCells::Bits(packed) => unpack_cells(packed, cell_count),
+            PackedCells::Bytes(cells) => cells.clone(),
+        }
+    }
+
+    /// Memory used by the cells
+    pub fn get_bytes(&self) -> usize {
+        match self {
+            PackedCells::Bits(data) | PackedCells::Bytes(data) => data.len(),
+        }
+    }
+}
+
 pub struct GoL {
     tex_a: wgpu::Texture,
     tex_b: wgpu::Texture,
@@ -6,7 +80,13 @@ pub struct GoL {
     tex_b_view: wgpu::TextureView,
     read_from_a: bool,
     pipeline: wgpu::RenderPipeline,
+    /// Kept to recreate the pipeline with another shader, see set_shader()
+    pipeline_layout: wgpu::PipelineLayout,
     bind_group_layout: wgpu::BindGroupLayout,
+    generation: u64,
+    /// False after writes that are not a generation (drawing, undo, loading) until the next update(),
+    /// the other texture is then not the generation before the current cells
+    has_previous: Cell<bool>,
 }
 impl GoL {
     pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
@@ -48,16 +128,36 @@ impl GoL {
             }],
         });
 
-        let shader_module = device.create_shader_module(wgpu::include_wgsl!("shaders.wgsl"));
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Game of Life Pipeline Layout"),
             push_constant_ranges: &[],
             bind_group_layouts: &[&bind_group_layout],
         });
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, SHADER_SOURCE);
+
+        Self {
+            tex_a,
+            tex_b,
+            tex_a_view,
+            tex_b_view,
+            read_from_a: true,
+            has_previous: Cell::new(false),
+            pipeline,
+            pipeline_layout,
+            bind_group_layout,
+            generation: 0,
+        }
+    }
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    /// Rule shader with vs_main and fs_main, cells of the last generation are at binding 0
+    fn create_pipeline(device: &wgpu::Device, pipeline_layout: &wgpu::PipelineLayout, source: &str) -> wgpu::RenderPipeline {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Game of Life Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Game of Life Pipeline"),
-            layout: Some(&pipeline_layout),
+            layout: Some(pipeline_layout),
             vertex: wgpu::VertexState {
                 compilation_options: Default::default(),
                 entry_point: Some("vs_main"),
@@ -69,7 +169,7 @@ impl GoL {
                 entry_point: Some("fs_main"),
                 compilation_options: Default::default(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: texture_format,
+                    format: wgpu::TextureFormat::R8Uint,
                     blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -91,17 +191,13 @@ impl GoL {
             },
             depth_stencil: None,
             cache: None,
-        });
+        })
+    }
 
-        Self {
-            tex_a,
-            tex_b,
-            tex_a_view,
-            tex_b_view,
-            read_from_a: true,
-            pipeline,
-            bind_group_layout,
-        }
+    /// Replaces the rule shader, e.g. with one edited on disk. The current pipeline stays when the new one doesn't compile
+    pub fn set_shader(&mut self, device: &wgpu::Device, source: &str) -> Result<(), String> {
+        self.pipeline = create_checked(device, || Self::create_pipeline(device, &self.pipeline_layout, source))?;
+        Ok(())
     }
 
     fn get_read_view(&self) -> &wgpu::TextureView {
@@ -125,6 +221,7 @@ impl GoL {
             &self.tex_a_view
         }
     }
+    #[allow(dead_code)]
     fn get_write_texture(&self) -> &wgpu::Texture {
         if self.read_from_a {
             &self.tex_b
@@ -143,6 +240,16 @@ impl GoL {
         self.get_read_view()
     }
 
+    /// Cells before the last update(), same format as get_current_view(). After edits made since then (drawing, undo, loading)
+    /// it's the current view, so there are no differences until the next generation
+    pub fn get_previous_view(&self) -> &wgpu::TextureView {
+        if self.has_previous.get() {
+            self.get_write_view()
+        } else {
+            self.get_read_view()
+        }
+    }
+
     /// I would like to make this function accept encoder, but this gives room for bugs.
     /// For example, if user calls update() multiple times before submitting the encoder, the state of the simulation will be wrong/some updates will be lost
     pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -182,10 +289,50 @@ impl GoL {
             render_pass.draw(0..6, 0..1);
         }
         self.read_from_a = !self.read_from_a;
+        self.generation += 1;
+        self.has_previous.set(true);
 
         queue.submit(Some(encoder.finish()));
     }
 
+    /// Copy of the whole grid that stays on the GPU, see restore_snapshot()
+    pub fn create_snapshot(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+        let texture = self.get_read_texture();
+        let snapshot = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Game of Life Snapshot"),
+            size: texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture.format(),
+            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_texture(texture.as_image_copy(), snapshot.as_image_copy(), texture.size());
+        queue.submit(Some(encoder.finish()));
+        snapshot
+    }
+
+    /// Snapshot has to be created from a grid of the same size
+    pub fn restore_snapshot(&self, device: &wgpu::Device, queue: &wgpu::Queue, snapshot: &wgpu::Texture) {
+        let texture = self.get_read_texture();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_texture(snapshot.as_image_copy(), texture.as_image_copy(), texture.size());
+        queue.submit(Some(encoder.finish()));
+        self.has_previous.set(false);
+    }
+
+    /// Number of update() calls since the grid was created
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Used when restoring saved state, grid contents should be written separately
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
     pub fn get_size(&self) -> (u32, u32) {
         (self.tex_a.size().width, self.tex_a.size().height)
     }
@@ -203,6 +350,7 @@ impl GoL {
         if width * height != data.len() as u32 {
             panic!("Data size does not match the area size");
         }
+        self.has_previous.set(false);
         queue.write_texture(
             wgpu::ImageCopyTexture {
                 texture: self.get_read_texture(),
@@ -224,6 +372,44 @@ impl GoL {
         )
     }
 
+    /// Same as write_area(), but area can be partially (or fully) outside of the texture, only the part inside is written.
+    pub fn write_area_clipped(
+        &self,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        x: i64,
+        y: i64,
+        width: u32,
+        height: u32,
+    ) {
+        if width as usize * height as usize != data.len() {
+            panic!("Data size does not match the area size");
+        }
+        let Some((start_x, start_y, clipped_width, clipped_height)) = self.clip_area(x, y, width, height) else {
+            return;
+        };
+        let mut clipped = Vec::with_capacity(clipped_width as usize * clipped_height as usize);
+        for row in start_y..start_y + clipped_height {
+            let row_start = (row as i64 - y) as usize * width as usize + (start_x as i64 - x) as usize;
+            clipped.extend_from_slice(&data[row_start..row_start + clipped_width as usize]);
+        }
+        self.write_area(queue, &clipped, start_x, start_y, clipped_width, clipped_height);
+    }
+
+    /// Part of the area that is inside the texture as (x, y, width, height), None if the area is fully outside
+    pub fn clip_area(&self, x: i64, y: i64, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (gol_width, gol_height) = self.get_size();
+        let start_x = x.max(0);
+        let start_y = y.max(0);
+        let end_x = (x + width as i64).min(gol_width as i64);
+        let end_y = (y + height as i64).min(gol_height as i64);
+        if start_x >= end_x || start_y >= end_y {
+            return None;
+        }
+        Some((start_x as u32, start_y as u32, (end_x - start_x) as u32, (end_y - start_y) as u32))
+    }
+
+    /// Reads cells of the area. Width doesn't need to be aligned, rows are padded internally for the copy and padding is stripped from the result.
     pub async fn read_area(
         &self,
         device: &wgpu::Device,
@@ -233,16 +419,70 @@ impl GoL {
         width: u32,
         height: u32,
     ) -> Vec<u8> {
-        if width % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT != 0 {
-            panic!(
-                "Width must be a multiple of {}",
-                wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
-            );
+        let (buffer, padded_width) = self.copy_area_to_buffer(device, queue, x, y, width, height);
+
+        let mut vec: Vec<u8> = Vec::with_capacity((width * height) as usize);
+
+        {
+            let buffer_slice = buffer.slice(..);
+
+            let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+            buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).unwrap()
+            });
+            device.poll(wgpu::Maintain::Wait);
+            receiver.receive().await.unwrap().unwrap();
+
+            let data = buffer_slice.get_mapped_range();
+
+            for row in data.chunks(padded_width as usize) {
+                vec.extend_from_slice(&row[..width as usize]);
+            }
         }
 
+        vec
+    }
+
+    /// Same as read_area(), but doesn't wait for the GPU. Use it when reading every frame/generation, so simulation doesn't stall.
+    pub fn start_area_read(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> PendingRead {
+        let (buffer, padded_width) = self.copy_area_to_buffer(device, queue, x, y, width, height);
+        let (sender, receiver) = mpsc::channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            // receiver is gone if read was abandoned
+            let _ = sender.send(result);
+        });
+        PendingRead {
+            buffer,
+            padded_width,
+            width,
+            receiver,
+        }
+    }
+
+    /// Returns mappable buffer and its bytes per row
+    fn copy_area_to_buffer(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Buffer, u32) {
+        let padded_width = width.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Read Area Buffer"),
-            size: (width * height) as wgpu::BufferAddress,
+            size: (padded_width * height) as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
@@ -260,7 +500,7 @@ impl GoL {
             },
             wgpu::ImageCopyBuffer {
                 layout: wgpu::ImageDataLayout {
-                    bytes_per_row: Some(width),
+                    bytes_per_row: Some(padded_width),
                     rows_per_image: Some(height),
                     offset: 0,
                 },
@@ -275,23 +515,6 @@ impl GoL {
 
         queue.submit(Some(encoder.finish()));
 
-        let mut vec: Vec<u8>;
-
-        {
-            let buffer_slice = buffer.slice(..);
-
-            let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
-            buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                sender.send(result).unwrap()
-            });
-            device.poll(wgpu::Maintain::Wait);
-            receiver.receive().await.unwrap().unwrap();
-
-            let data = buffer_slice.get_mapped_range();
-
-            vec = data.to_vec();
-        }
-
-        vec
+        (buffer, padded_width)
     }
 }
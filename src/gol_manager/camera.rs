@@ -1,14 +1,105 @@
 use glam::{vec2, Mat3, Vec2};
-use winit::event::{ElementState, KeyEvent, WindowEvent};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::keyboard::{KeyCode, PhysicalKey};
 
+/// How fast smoothed values approach their targets, per second. Bigger is snappier
+const SMOOTHING: f32 = 15.0;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 100.0;
+/// Zoom factor per wheel line
+const ZOOM_STEP: f32 = 0.85;
+/// Touchpads report pixels instead of lines
+const PIXELS_PER_WHEEL_LINE: f32 = 40.0;
+/// Radians per second while Q or E is held
+const ROTATION_SPEED: f32 = std::f32::consts::FRAC_PI_2;
+const TRANSITION_DURATION: Duration = Duration::from_millis(600);
+
+/// Everything that defines what the camera shows, except the window aspect ratio
+#[derive(Clone, Copy)]
+pub struct CameraView {
+    pub position: Vec2,
+    pub zoom: f32,
+    pub rotation: f32,
+}
+
+pub struct ViewBookmark {
+    pub name: String,
+    pub view: CameraView,
+}
+
+/// Cells of the grid in world units, integer zoom snaps the camera to them
+#[derive(Clone, Copy)]
+pub struct PixelGrid {
+    /// Top left corner of the grid
+    pub corner: Vec2,
+    pub cell_size: f32,
+}
+
+impl PixelGrid {
+    fn get_pixels_per_cell(&self, zoom: f32, screen_height: f32) -> f32 {
+        // zoom is half of the screen height in world units
+        self.cell_size * screen_height / (2.0 * zoom)
+    }
+    fn get_zoom(&self, pixels_per_cell: f32, screen_height: f32) -> f32 {
+        self.cell_size * screen_height / (2.0 * pixels_per_cell)
+    }
+}
+
+/// Whole pixels per cell, or whole cells per pixel when zoomed out
+fn round_pixels_per_cell(pixels_per_cell: f32) -> f32 {
+    if pixels_per_cell >= 1.0 {
+        pixels_per_cell.round()
+    } else {
+        1.0 / (1.0 / pixels_per_cell).round()
+    }
+}
+
+/// Next whole level after the rounded one
+fn step_pixels_per_cell(pixels_per_cell: f32, is_zooming_in: bool) -> f32 {
+    let rounded = round_pixels_per_cell(pixels_per_cell);
+    match (rounded >= 1.0, is_zooming_in) {
+        (true, true) => rounded + 1.0,
+        (true, false) if rounded > 1.0 => rounded - 1.0,
+        (false, true) if rounded < 1.0 => 1.0 / (1.0 / rounded - 1.0),
+        _ => 1.0 / (1.0 / rounded + 1.0),
+    }
+}
+
+/// Animated move between two views, see CameraController::animate_to()
+struct Transition {
+    from: CameraView,
+    to: CameraView,
+    start: Instant,
+}
+
 pub struct CameraController {
+    /// Screen heights per second
     speed: f32,
     is_up_pressed: bool,
     is_down_pressed: bool,
     is_left_pressed: bool,
     is_right_pressed: bool,
-    wheel: f32,
+    is_rotate_left_pressed: bool,
+    is_rotate_right_pressed: bool,
+    /// Smoothed WASD movement in world units per second
+    velocity: Vec2,
+    /// Wheel changes target zoom right away, camera zoom follows it smoothly
+    target_zoom: Option<f32>,
+    /// Physical pixels
+    cursor_position: Option<Vec2>,
+    window_size: Vec2,
+    /// World point grabbed with middle button, it's kept under the cursor while dragging
+    drag_anchor: Option<Vec2>,
+    /// Canceled by any manual movement
+    transition: Option<Transition>,
+    /// World point the camera is kept centered on, e.g. a tracked spaceship
+    follow_target: Option<Vec2>,
+    /// Integer zoom, when set the camera settles where every cell covers whole pixels and cell edges are on pixel edges
+    pixel_snapping: Option<PixelGrid>,
+    last_update: Instant,
 }
 
 impl CameraController {
@@ -19,10 +110,21 @@ impl CameraController {
             is_down_pressed: false,
             is_left_pressed: false,
             is_right_pressed: false,
-            wheel: 0.0,
+            is_rotate_left_pressed: false,
+            is_rotate_right_pressed: false,
+            velocity: Vec2::ZERO,
+            target_zoom: None,
+            cursor_position: None,
+            window_size: Vec2::ONE,
+            drag_anchor: None,
+            transition: None,
+            follow_target: None,
+            pixel_snapping: None,
+            last_update: Instant::now(),
         }
     }
-    pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
+    pub fn handle_input(&mut self, event: &WindowEvent, window_size: PhysicalSize<u32>, camera: &Camera) -> bool {
+        self.window_size = vec2(window_size.width.max(1) as f32, window_size.height.max(1) as f32);
         match event {
             WindowEvent::KeyboardInput {
                 event:
@@ -51,24 +153,126 @@ impl CameraController {
                         self.is_right_pressed = pressed;
                         true
                     }
+                    KeyCode::KeyQ => {
+                        self.is_rotate_left_pressed = pressed;
+                        true
+                    }
+                    KeyCode::KeyE => {
+                        self.is_rotate_right_pressed = pressed;
+                        true
+                    }
                     _ => false,
                 }
             }
             WindowEvent::MouseWheel { delta, .. } => {
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        self.wheel = *y;
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(pos) => {
-                        self.wheel = pos.y as f32;
+                let lines = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => *y,
+                    winit::event::MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / PIXELS_PER_WHEEL_LINE,
+                };
+                self.transition = None;
+                let current_zoom = self.target_zoom.unwrap_or(camera.zoom);
+                let mut zoom = current_zoom * ZOOM_STEP.powf(lines);
+                if let Some(grid) = self.pixel_snapping {
+                    // wheel steps are smaller than whole pixels when zoomed out, so rounding alone could get stuck
+                    let pixels_per_cell = grid.get_pixels_per_cell(zoom, self.window_size.y);
+                    let current = round_pixels_per_cell(grid.get_pixels_per_cell(current_zoom, self.window_size.y));
+                    let mut snapped = round_pixels_per_cell(pixels_per_cell);
+                    if snapped == current && lines != 0.0 {
+                        snapped = step_pixels_per_cell(current, lines > 0.0);
                     }
+                    zoom = grid.get_zoom(snapped, self.window_size.y);
                 }
+                self.target_zoom = Some(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+                true
+            }
+            WindowEvent::MouseInput { button: MouseButton::Middle, state, .. } => {
+                self.transition = None;
+                self.drag_anchor = match (state, self.get_cursor_ndc()) {
+                    (ElementState::Pressed, Some(ndc)) => Some(camera.ndc_to_world(ndc)),
+                    _ => None,
+                };
                 true
             }
+            // drawing needs cursor position too, so it's not consumed
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(vec2(position.x as f32, position.y as f32));
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+                false
+            }
             _ => false,
         }
     }
+    /// While set, panning is disabled and zoom is around the screen center
+    pub fn set_follow_target(&mut self, target: Option<Vec2>) {
+        self.follow_target = target;
+        if target.is_some() {
+            self.transition = None;
+            self.drag_anchor = None;
+        }
+    }
+    /// Forgets held keys and the middle button drag, e.g. when input goes to another controller before they're released
+    pub fn release_input(&mut self) {
+        self.is_up_pressed = false;
+        self.is_down_pressed = false;
+        self.is_left_pressed = false;
+        self.is_right_pressed = false;
+        self.is_rotate_left_pressed = false;
+        self.is_rotate_right_pressed = false;
+        self.drag_anchor = None;
+    }
+    /// Called every frame, snapping needs the exact window size
+    pub fn set_pixel_snapping(&mut self, grid: Option<PixelGrid>, window_size: PhysicalSize<u32>) {
+        self.pixel_snapping = grid;
+        self.window_size = vec2(window_size.width.max(1) as f32, window_size.height.max(1) as f32);
+    }
+    /// Centers the camera on the world point right away, e.g. when dragging on the minimap
+    pub fn move_to(&mut self, camera: &mut Camera, position: Vec2) {
+        self.transition = None;
+        self.drag_anchor = None;
+        self.velocity = Vec2::ZERO;
+        camera.position = position;
+    }
+    /// Moves the camera to the view over TRANSITION_DURATION
+    pub fn animate_to(&mut self, camera: &Camera, view: CameraView) {
+        self.target_zoom = None;
+        self.transition = Some(Transition {
+            from: camera.get_view(),
+            to: CameraView {
+                zoom: view.zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+                ..view
+            },
+            start: Instant::now(),
+        });
+    }
+    /// View showing the whole rectangle (in world units) with a small margin, rotation is kept
+    pub fn get_view_fitting(camera: &Camera, min: Vec2, max: Vec2) -> CameraView {
+        let size = (max - min) * 1.1;
+        // size of the rectangle on the screen when rotated
+        let (sin, cos) = (camera.rotation.sin().abs(), camera.rotation.cos().abs());
+        let screen_size = vec2(size.x * cos + size.y * sin, size.x * sin + size.y * cos);
+        // zoom is half of the screen height in world units
+        let zoom = (screen_size.y / 2.0).max(screen_size.x / 2.0 / camera.aspect_ratio);
+        CameraView {
+            position: (min + max) / 2.0,
+            zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+            rotation: camera.rotation,
+        }
+    }
+    fn get_cursor_ndc(&self) -> Option<Vec2> {
+        let ndc = self.cursor_position? / self.window_size * 2.0 - Vec2::ONE;
+        Some(vec2(ndc.x, -ndc.y))
+    }
+    /// Movement depends on time since the last call, not on the frame rate
     pub fn update_camera(&mut self, camera: &mut Camera) {
+        // long pauses (e.g. window was dragged) shouldn't make the camera jump
+        let dt = self.last_update.elapsed().as_secs_f32().min(0.1);
+        self.last_update = Instant::now();
+        // share of the remaining distance to the target covered this frame
+        let blend = 1.0 - (-SMOOTHING * dt).exp();
+
         let mut movement = Vec2::ZERO;
         if self.is_up_pressed {
             movement.y += 1.0;
@@ -82,10 +286,83 @@ impl CameraController {
         if self.is_right_pressed {
             movement.x += 1.0;
         }
-        camera.zoom *= 1.0 - self.wheel * 0.04;
-        camera.zoom = camera.zoom.clamp(0.1, 100.0);
-        camera.position += movement.normalize_or_zero() * self.speed * camera.zoom;
-        self.wheel = 0.0;
+        if self.follow_target.is_some() {
+            movement = Vec2::ZERO;
+        }
+        if movement != Vec2::ZERO {
+            self.transition = None;
+        }
+        // WASD moves along the screen axes, not the grid axes
+        let target_velocity = Vec2::from_angle(camera.rotation).rotate(movement.normalize_or_zero()) * self.speed * camera.zoom;
+        self.velocity += (target_velocity - self.velocity) * blend;
+        camera.position += self.velocity * dt;
+
+        let rotation_direction = self.is_rotate_left_pressed as i32 - self.is_rotate_right_pressed as i32;
+        if rotation_direction != 0 {
+            self.transition = None;
+            // around the screen center, so the camera position doesn't change
+            camera.rotation = wrap_angle(camera.rotation + rotation_direction as f32 * ROTATION_SPEED * dt);
+        }
+
+        if let Some(transition) = &self.transition {
+            let t = (transition.start.elapsed().as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0);
+            // smoothstep, starts and ends slowly
+            let t_smooth = t * t * (3.0 - 2.0 * t);
+            camera.set_view(interpolate_views(&transition.from, &transition.to, t_smooth));
+            if t >= 1.0 {
+                self.transition = None;
+            }
+        }
+
+        if let Some(target_zoom) = self.target_zoom {
+            // zoom around the cursor, the point under it stays in place
+            let anchor_ndc = self.get_cursor_ndc().filter(|_| self.follow_target.is_none()).unwrap_or(Vec2::ZERO);
+            let anchor = camera.ndc_to_world(anchor_ndc);
+            camera.zoom += (target_zoom - camera.zoom) * blend;
+            if (camera.zoom / target_zoom - 1.0).abs() < 0.001 {
+                camera.zoom = target_zoom;
+                self.target_zoom = None;
+            }
+            camera.position += anchor - camera.ndc_to_world(anchor_ndc);
+        }
+
+        if let (Some(drag_anchor), Some(ndc), None) = (self.drag_anchor, self.get_cursor_ndc(), self.follow_target) {
+            camera.position += drag_anchor - camera.ndc_to_world(ndc);
+        }
+
+        if let Some(target) = self.follow_target {
+            camera.position += (target - camera.position) * blend;
+        }
+
+        let is_moving = movement != Vec2::ZERO
+            || rotation_direction != 0
+            || self.transition.is_some()
+            || self.target_zoom.is_some()
+            || self.drag_anchor.is_some();
+        if let (Some(grid), false) = (self.pixel_snapping, is_moving) {
+            self.snap_to_pixels(camera, grid);
+        }
+    }
+    /// Rounds zoom to whole pixels per cell and moves the camera by less than a pixel so cell edges are on pixel edges.
+    /// Cells can't line up with pixels when the view is rotated, except by quarter turns
+    fn snap_to_pixels(&self, camera: &mut Camera, grid: PixelGrid) {
+        let quarter_turns = camera.rotation / FRAC_PI_2;
+        if (quarter_turns - quarter_turns.round()).abs() > 0.001 {
+            return;
+        }
+        camera.rotation = wrap_angle(quarter_turns.round() * FRAC_PI_2);
+        let pixels_per_cell = round_pixels_per_cell(grid.get_pixels_per_cell(camera.zoom, self.window_size.y));
+        let zoom = grid.get_zoom(pixels_per_cell, self.window_size.y);
+        if (MIN_ZOOM..=MAX_ZOOM).contains(&zoom) {
+            camera.zoom = zoom;
+        }
+
+        let corner_ndc = (camera.get_matrix() * grid.corner.extend(1.0)).truncate();
+        let corner_pixel = (corner_ndc * vec2(1.0, -1.0) + Vec2::ONE) / 2.0 * self.window_size;
+        let pixel_offset = corner_pixel.round() - corner_pixel;
+        // camera moves the other way than the image
+        let ndc_offset = pixel_offset / self.window_size * vec2(2.0, -2.0);
+        camera.position -= camera.ndc_to_world(ndc_offset) - camera.ndc_to_world(Vec2::ZERO);
     }
 }
 
@@ -108,6 +385,25 @@ impl Camera {
         projection * view
     }
 
+    pub fn get_view(&self) -> CameraView {
+        CameraView {
+            position: self.position,
+            zoom: self.zoom,
+            rotation: self.rotation,
+        }
+    }
+
+    pub fn set_view(&mut self, view: CameraView) {
+        self.position = view.position;
+        self.zoom = view.zoom;
+        self.rotation = view.rotation;
+    }
+
+    /// World point shown at the given normalized device coordinates
+    pub fn ndc_to_world(&self, ndc: Vec2) -> Vec2 {
+        (self.get_matrix().inverse() * ndc.extend(1.0)).truncate()
+    }
+
     pub fn new(aspect_ratio: f32) -> Self {
         Self {
             position: Vec2::ZERO,
@@ -116,4 +412,19 @@ impl Camera {
             aspect_ratio,
         }
     }
-}
\ No newline at end of file
+}
+/// Angle in -PI..PI
+pub fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Zoom is interpolated geometrically so zooming in and out feels equally fast,
+/// rotation takes the shorter way around
+fn interpolate_views(from: &CameraView, to: &CameraView, t: f32) -> CameraView {
+    let rotation_difference = wrap_angle(to.rotation - from.rotation);
+    CameraView {
+        position: from.position.lerp(to.position, t),
+        zoom: from.zoom * (to.zoom / from.zoom).powf(t),
+        rotation: wrap_angle(from.rotation + rotation_difference * t),
+    }
+}
@@ -1,25 +1,909 @@
 use egui::{Align2, Context};
+use std::path::PathBuf;
+use crate::drawing::{BrushShape, Connectivity, GoLDrawing, SymmetryMode, TextFont, TextOptions, Tool, MAX_BRUSH_SIZE, MAX_TEXT_SCALE};
+use crate::gol_manager::camera::ViewBookmark;
+use crate::gol_manager::viewports::{ViewportLayout, ViewportSettings, MAX_VIEWPORTS};
 use crate::gol_manager::GoLConfig;
+use crate::gol_renderer::{
+    CellShape, GridSettings, MinimapCorner, MinimapSettings, Theme, TrailSettings, DEFAULT_THEMES_PATH, MAX_MAJOR_GRID_STEP, MAX_MINIMAP_SIZE,
+    MAX_STATES, MIN_MINIMAP_SIZE, MIN_TRAIL_DECAY,
+};
+use crate::gol_manager::library_gui::{add_library_window, LibraryGui};
+use crate::pattern_io::{Binarization, ImageImportOptions, ImagePalette, PatternFormat};
+use crate::pattern_library::PatternLibrary;
+use crate::screenshot::{ScreenshotKind, DEFAULT_SCREENSHOT_PATH, MAX_SCREENSHOT_SIZE};
+use crate::shader_reload::{ShaderKind, DEFAULT_RENDER_SHADER_PATH, DEFAULT_SIMULATION_SHADER_PATH};
+use crate::recorder::AnimationFormat;
+use crate::session::DEFAULT_EXTENSION;
+use winit::dpi::PhysicalSize;
 
-pub fn add_gui(ui: &Context, fps_text: &str, gol_config: &mut GoLConfig) {
+/// Outline of the focused view, same as the visible area on the minimap
+const FOCUSED_VIEW_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 204, 51);
+const VIEW_BORDER_COLOR: egui::Color32 = egui::Color32::from_gray(90);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportRegion {
+    /// Bounding box of all alive cells in the grid
+    AliveCells,
+    VisibleArea,
+    /// Selected cells, whether alive or dead
+    Selection,
+}
+
+impl ExportRegion {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportRegion::AliveCells => "All alive cells",
+            ExportRegion::VisibleArea => "Visible area",
+            ExportRegion::Selection => "Selection",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Pattern(PatternFormat),
+    /// One pixel per cell, colored with GuiState::image_palette
+    Png,
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Pattern(format) => format.name(),
+            ExportFormat::Png => "PNG image (.png)",
+        }
+    }
+}
+
+/// Part of the loaded macrocell pattern that will be placed into the grid. Offset is relative to the pattern's top left alive cell
+pub struct MacrocellWindow {
+    pub content_size: (u64, u64),
+    pub alive_cells: u64,
+    pub x: u64,
+    pub y: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Window is never bigger than the grid, it's placed densely
+    pub max_size: (u32, u32),
+}
+
+pub struct ViewGui {
+    /// Updated by GoLManager every frame, slider changes are sent as GuiAction::SetRotation
+    pub rotation_degrees: f32,
+    /// Bookmark N (from 1) is recalled with key N and overwritten with Ctrl+N
+    pub bookmarks: Vec<ViewBookmark>,
+    pub new_bookmark_name: String,
+    /// Status of the tracked object, None when nothing is tracked. Updated by GoLManager every frame
+    pub tracking: Option<String>,
+    pub minimap: MinimapSettings,
+    pub grid: GridSettings,
+    pub trail: TrailSettings,
+    /// Cells coloured by the last generation's changes instead of the theme
+    pub is_showing_diff: bool,
+    /// Zoom settles on whole pixels per cell, see CameraController::set_pixel_snapping()
+    pub is_pixel_snapping: bool,
+    pub viewports: ViewportSettings,
+    /// View that camera buttons and shortcuts act on. Updated by GoLManager every frame
+    pub focused_viewport: usize,
+}
+
+pub struct ThemeGui {
+    /// Built-in themes first, then custom ones. Edits are applied live, GoLManager uploads the selected theme when it changes
+    pub themes: Vec<Theme>,
+    pub selected: usize,
+    pub path: String,
+}
+
+impl ThemeGui {
+    pub fn get_theme(&self) -> &Theme {
+        &self.themes[self.selected]
+    }
+}
+
+pub struct RecordingGui {
+    pub path: String,
+    pub format: AnimationFormat,
+    /// x, y, width, height in cells
+    pub region: [u32; 4],
+    pub every_generations: u32,
+    pub scale: u32,
+    pub frame_delay_ms: u32,
+    pub palette: ImagePalette,
+    /// Updated by GoLManager every frame, None when nothing is being recorded
+    pub progress: Option<String>,
+}
+
+pub struct ScreenshotGui {
+    pub path: String,
+    /// Size of simulation screenshots, window screenshots have the window size
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct ShaderGui {
+    /// Shaders are reloaded from the files whenever they change
+    pub is_watching: bool,
+    pub simulation_path: String,
+    pub render_path: String,
+    /// Compiler messages of the last reload, None when it worked. Updated by GoLManager
+    pub simulation_error: Option<String>,
+    pub render_error: Option<String>,
+}
+
+impl ShaderGui {
+    pub fn get_path(&self, kind: ShaderKind) -> &str {
+        match kind {
+            ShaderKind::Simulation => &self.simulation_path,
+            ShaderKind::Render => &self.render_path,
+        }
+    }
+
+    fn get_path_mut(&mut self, kind: ShaderKind) -> &mut String {
+        match kind {
+            ShaderKind::Simulation => &mut self.simulation_path,
+            ShaderKind::Render => &mut self.render_path,
+        }
+    }
+
+    pub fn set_error(&mut self, kind: ShaderKind, error: Option<String>) {
+        match kind {
+            ShaderKind::Simulation => self.simulation_error = error,
+            ShaderKind::Render => self.render_error = error,
+        }
+    }
+
+    fn get_error(&self, kind: ShaderKind) -> Option<&String> {
+        match kind {
+            ShaderKind::Simulation => self.simulation_error.as_ref(),
+            ShaderKind::Render => self.render_error.as_ref(),
+        }
+    }
+}
+
+pub struct GuiState {
+    pub pattern_path: String,
+    pub export_format: ExportFormat,
+    pub export_region: ExportRegion,
+    pub image_palette: ImagePalette,
+    /// Used when imported file is PNG image
+    pub image_options: ImageImportOptions,
+    /// Set when macrocell file is imported, macrocell itself is kept by GoLManager
+    pub macrocell_window: Option<MacrocellWindow>,
+    pub recording: RecordingGui,
+    pub screenshot: ScreenshotGui,
+    pub shaders: ShaderGui,
+    pub session_path: String,
+    pub view: ViewGui,
+    pub theme: ThemeGui,
+    pub library: LibraryGui,
+    /// Share of alive cells after random fill of the selection
+    pub fill_density: f32,
+    /// Rendered with text_options and armed for placement by GuiAction::ArmText
+    pub text: String,
+    pub text_options: TextOptions,
+    /// Updated by GoLManager every frame, undo/redo buttons are disabled when there's nothing to do
+    pub undo_count: usize,
+    pub redo_count: usize,
+    /// Result of the last action that can fail, e.g. "Import failed: line 3: ..."
+    pub status: Option<String>,
+}
+
+impl GuiState {
+    pub fn new() -> Self {
+        Self {
+            pattern_path: String::new(),
+            export_format: ExportFormat::Pattern(PatternFormat::Plaintext),
+            export_region: ExportRegion::AliveCells,
+            image_palette: ImagePalette::Monochrome,
+            image_options: ImageImportOptions {
+                max_width: 500,
+                max_height: 500,
+                upscale: true,
+                binarization: Binarization::Threshold,
+                threshold: 0.5,
+                invert: false,
+            },
+            macrocell_window: None,
+            recording: RecordingGui {
+                path: "recording.gif".to_string(),
+                format: AnimationFormat::Gif,
+                region: [0, 0, 200, 200],
+                every_generations: 1,
+                scale: 2,
+                frame_delay_ms: 100,
+                palette: ImagePalette::Monochrome,
+                progress: None,
+            },
+            screenshot: ScreenshotGui {
+                path: DEFAULT_SCREENSHOT_PATH.to_string(),
+                width: 7680,
+                height: 4320,
+            },
+            shaders: ShaderGui {
+                is_watching: false,
+                simulation_path: DEFAULT_SIMULATION_SHADER_PATH.to_string(),
+                render_path: DEFAULT_RENDER_SHADER_PATH.to_string(),
+                simulation_error: None,
+                render_error: None,
+            },
+            session_path: format!("session.{}", DEFAULT_EXTENSION),
+            view: ViewGui {
+                rotation_degrees: 0.0,
+                bookmarks: Vec::new(),
+                new_bookmark_name: String::new(),
+                tracking: None,
+                minimap: MinimapSettings::new(),
+                grid: GridSettings::new(),
+                trail: TrailSettings::new(),
+                is_showing_diff: false,
+                is_pixel_snapping: false,
+                viewports: ViewportSettings::new(),
+                focused_viewport: 0,
+            },
+            theme: ThemeGui {
+                themes: Theme::built_in(),
+                selected: 0,
+                path: DEFAULT_THEMES_PATH.to_string(),
+            },
+            library: LibraryGui::new(),
+            fill_density: 0.5,
+            text: String::new(),
+            text_options: TextOptions {
+                font: TextFont::Normal,
+                scale: 1,
+                letter_spacing: 2,
+                line_spacing: 3,
+                outline: false,
+            },
+            undo_count: 0,
+            redo_count: 0,
+            status: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SelectionOperation {
+    Copy,
+    Cut,
+    Clear,
+    Invert,
+    /// Uses GuiState::fill_density
+    RandomFill,
+}
+
+/// Things GUI can't do on its own (they need device/queue), GoLManager handles them after the frame is drawn
+pub enum GuiAction {
+    ImportPattern,
+    ExportPattern,
+    PlaceMacrocellWindow,
+    StartRecording,
+    StopRecording,
+    /// Sets recording region to the part of the grid visible on the screen
+    RecordVisibleArea,
+    SaveSession,
+    LoadSession,
+    /// Index into PatternLibrary::entries
+    ArmLibraryPattern(usize),
+    /// Adds LibraryGui::new_directory
+    AddLibraryDirectory,
+    RemoveLibraryDirectory(PathBuf),
+    RescanLibrary,
+    EditSelection(SelectionOperation),
+    SelectAll,
+    /// Arms clipboard contents for placement
+    Paste,
+    Undo,
+    Redo,
+    /// Renders GuiState::text and arms it for placement
+    ArmText,
+    /// Radians
+    SetRotation(f32),
+    /// Frames the bounding box of alive cells
+    FitToContent,
+    FitToSelection,
+    /// Starts following the object in the selection, or stops following
+    ToggleTracking,
+    ToggleGrid,
+    /// Advances one generation, only while paused
+    Step,
+    /// Saved to ScreenshotGui::path
+    Screenshot(ScreenshotKind),
+    /// Stops watching shader files and goes back to the compiled in shaders
+    ResetShaders,
+    /// Overwrites the bookmark at the index, or adds a new one named ViewGui::new_bookmark_name
+    SaveBookmark(Option<usize>),
+    GoToBookmark(usize),
+    DeleteBookmark(usize),
+    /// Replaces custom themes with the ones in ThemeGui::path
+    LoadThemes,
+    SaveThemes,
+}
+
+pub fn add_gui(
+    ui: &Context,
+    fps_text: &str,
+    gol_config: &mut GoLConfig,
+    gui_state: &mut GuiState,
+    library: &PatternLibrary,
+    drawing: &mut GoLDrawing,
+) -> Vec<GuiAction> {
+    let mut actions = Vec::new();
     egui::Window::new("Foff")
         .default_open(true)
         .default_width(800.0)
         .resizable(true)
         .anchor(Align2::LEFT_TOP, [0.0, 0.0])
-        .show(&ui, |mut ui| {
+        .show(ui, |ui| {
             ui.add(egui::Label::new(fps_text));
 
-            let pause_text = if gol_config.is_paused { "Resume" } else { "Pause" };
+            ui.horizontal(|ui| {
+                let pause_text = if gol_config.is_paused { "Resume" } else { "Pause" };
 
-            let pause_button_response = ui.add(egui::Button::new(pause_text)).on_hover_text("Pause/Resume the simulation");
-            if pause_button_response.clicked() {
-                gol_config.is_paused = !gol_config.is_paused;
-            }
+                let pause_button_response = ui.add(egui::Button::new(pause_text)).on_hover_text("Pause/Resume the simulation");
+                if pause_button_response.clicked() {
+                    gol_config.is_paused = !gol_config.is_paused;
+                }
+                let step_button = ui.add_enabled(gol_config.is_paused, egui::Button::new("Step"));
+                if step_button.on_hover_text("N, one generation while paused").clicked() {
+                    actions.push(GuiAction::Step);
+                }
+                ui.checkbox(&mut gui_state.view.is_showing_diff, "Births and deaths")
+                    .on_hover_text("Colours cells born (green), died (red) and survived (grey) in the last generation");
+            });
 
             let slider = egui::Slider::new(&mut gol_config.target_tps, 1..=1000).text("Target TPS").logarithmic(true);
-            let tps_slider_response = ui.add(slider);
+            ui.add(slider);
 
             ui.end_row();
+
+            ui.horizontal(|ui| {
+                let undo_button = ui.add_enabled(gui_state.undo_count > 0, egui::Button::new("Undo"));
+                if undo_button.on_hover_text("Ctrl+Z, rewinds the simulation to the edit if it has run since").clicked() {
+                    actions.push(GuiAction::Undo);
+                }
+                let redo_button = ui.add_enabled(gui_state.redo_count > 0, egui::Button::new("Redo"));
+                if redo_button.on_hover_text("Ctrl+Y or Ctrl+Shift+Z").clicked() {
+                    actions.push(GuiAction::Redo);
+                }
+            });
+
+            if ui.button("Pattern library").on_hover_text("Browse built-in and saved patterns").clicked() {
+                gui_state.library.is_open = !gui_state.library.is_open;
+            }
+
+            ui.collapsing("View", |ui| {
+                ui.label("WASD or middle drag moves, wheel zooms, Q/E rotates");
+                let view = &mut gui_state.view;
+                ui.horizontal(|ui| {
+                    let slider = egui::Slider::new(&mut view.rotation_degrees, -180.0..=180.0).text("Rotation").suffix("°");
+                    if ui.add(slider).changed() {
+                        actions.push(GuiAction::SetRotation(view.rotation_degrees.to_radians()));
+                    }
+                    if ui.button("Reset").clicked() {
+                        actions.push(GuiAction::SetRotation(0.0));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Fit to pattern").on_hover_text("Home").clicked() {
+                        actions.push(GuiAction::FitToContent);
+                    }
+                    if ui.button("Fit to selection").clicked() {
+                        actions.push(GuiAction::FitToSelection);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let label = if view.tracking.is_some() { "Stop tracking" } else { "Track selection" };
+                    if ui.button(label).on_hover_text("T").clicked() {
+                        actions.push(GuiAction::ToggleTracking);
+                    }
+                    if let Some(tracking) = &view.tracking {
+                        ui.label(tracking);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let viewports = &mut view.viewports;
+                    egui::ComboBox::from_label("Views")
+                        .selected_text(viewports.layout.name())
+                        .show_ui(ui, |ui| {
+                            for layout in ViewportLayout::ALL {
+                                ui.selectable_value(&mut viewports.layout, layout, layout.name());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Input goes to the view under the cursor, buttons and shortcuts to the outlined one");
+                    if viewports.layout == ViewportLayout::PictureInPicture {
+                        let count = viewports.insets.len();
+                        if ui.add_enabled(count < MAX_VIEWPORTS - 1, egui::Button::new("Add")).clicked() {
+                            viewports.insets.push(ViewportSettings::get_default_inset(count));
+                        }
+                        if ui.add_enabled(count > 1, egui::Button::new("Remove")).clicked() {
+                            viewports.insets.pop();
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let minimap = &mut view.minimap;
+                    ui.checkbox(&mut minimap.is_visible, "Minimap");
+                    ui.add_enabled_ui(minimap.is_visible, |ui| {
+                        ui.add(egui::Slider::new(&mut minimap.size, MIN_MINIMAP_SIZE..=MAX_MINIMAP_SIZE).suffix(" px"));
+                        egui::ComboBox::from_id_salt("minimap_corner")
+                            .selected_text(minimap.corner.name())
+                            .show_ui(ui, |ui| {
+                                for corner in MinimapCorner::ALL {
+                                    ui.selectable_value(&mut minimap.corner, corner, corner.name());
+                                }
+                            });
+                    });
+                });
+                ui.horizontal(|ui| {
+                    let grid = &mut view.grid;
+                    ui.checkbox(&mut grid.is_visible, "Grid lines").on_hover_text("G");
+                    ui.add_enabled_ui(grid.is_visible, |ui| {
+                        ui.label("Major every");
+                        ui.add(egui::DragValue::new(&mut grid.major_step).range(0..=MAX_MAJOR_GRID_STEP))
+                            .on_hover_text("0 hides major lines");
+                    });
+                });
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Cell shape")
+                        .selected_text(view.grid.cell_shape.name())
+                        .show_ui(ui, |ui| {
+                            for shape in CellShape::ALL {
+                                ui.selectable_value(&mut view.grid.cell_shape, shape, shape.name());
+                            }
+                        });
+                    ui.checkbox(&mut view.is_pixel_snapping, "Integer zoom")
+                        .on_hover_text("Every cell covers a whole number of pixels, needs rotation in quarter turns");
+                });
+                ui.horizontal(|ui| {
+                    let trail = &mut view.trail;
+                    ui.checkbox(&mut trail.is_enabled, "Trails").on_hover_text("Where cells were alive recently");
+                    ui.add_enabled_ui(trail.is_enabled, |ui| {
+                        ui.color_edit_button_rgb(&mut trail.color);
+                        ui.add(
+                            egui::Slider::new(&mut trail.decay, MIN_TRAIL_DECAY..=1.0)
+                                .logarithmic(true)
+                                .text("Fade per generation"),
+                        );
+                    });
+                });
+                ui.label("Bookmarks");
+                for (index, bookmark) in view.bookmarks.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Go").on_hover_text(format!("Key {}", index + 1)).clicked() {
+                            actions.push(GuiAction::GoToBookmark(index));
+                        }
+                        ui.text_edit_singleline(&mut bookmark.name);
+                        if ui.small_button("Update").on_hover_text(format!("Save current view (Ctrl+{})", index + 1)).clicked() {
+                            actions.push(GuiAction::SaveBookmark(Some(index)));
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            actions.push(GuiAction::DeleteBookmark(index));
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut view.new_bookmark_name).hint_text("Name"));
+                    if ui.button("Add").on_hover_text("Save current view").clicked() {
+                        actions.push(GuiAction::SaveBookmark(None));
+                    }
+                });
+            });
+
+            ui.collapsing("Theme", |ui| {
+                let theme_gui = &mut gui_state.theme;
+                egui::ComboBox::from_label("Theme")
+                    .selected_text(&theme_gui.get_theme().name)
+                    .show_ui(ui, |ui| {
+                        for (index, theme) in theme_gui.themes.iter().enumerate() {
+                            ui.selectable_value(&mut theme_gui.selected, index, &theme.name);
+                        }
+                    });
+                let theme = &mut theme_gui.themes[theme_gui.selected];
+                let mut copy = None;
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut theme.name);
+                });
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_rgb(&mut theme.background);
+                    ui.label("Background");
+                });
+                ui.label("States");
+                ui.horizontal_wrapped(|ui| {
+                    for (state, color) in theme.colors.iter_mut().enumerate() {
+                        let hint = match state {
+                            0 => "Dead".to_string(),
+                            1 => "Alive".to_string(),
+                            state => format!("State {}", state),
+                        };
+                        ui.color_edit_button_rgb(color).on_hover_text(hint);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(theme.colors.len() < MAX_STATES, egui::Button::new("Add state")).clicked() {
+                        let last = *theme.colors.last().unwrap();
+                        theme.colors.push(last);
+                    }
+                    if ui.add_enabled(theme.colors.len() > 2, egui::Button::new("Remove state")).clicked() {
+                        theme.colors.pop();
+                    }
+                    if ui.button("Copy").on_hover_text("New theme based on this one").clicked() {
+                        copy = Some(Theme {
+                            name: format!("{} copy", theme.name),
+                            ..theme.clone()
+                        });
+                    }
+                });
+                if let Some(copy) = copy {
+                    theme_gui.themes.push(copy);
+                    theme_gui.selected = theme_gui.themes.len() - 1;
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut theme_gui.path);
+                    if ui.button("Save").on_hover_text("Saves all themes").clicked() {
+                        actions.push(GuiAction::SaveThemes);
+                    }
+                    if ui.button("Load").clicked() {
+                        actions.push(GuiAction::LoadThemes);
+                    }
+                });
+            });
+
+            ui.collapsing("Tools", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for tool in Tool::ALL {
+                        ui.radio_value(&mut drawing.tool, tool, tool.name());
+                    }
+                });
+                match drawing.tool {
+                    Tool::Brush => {
+                        let brush = &mut drawing.brush;
+                        ui.label("Left drag draws, right drag erases");
+                        ui.horizontal(|ui| {
+                            for shape in BrushShape::ALL {
+                                ui.radio_value(&mut brush.shape, shape, shape.name());
+                            }
+                        });
+                        ui.add(egui::Slider::new(&mut brush.size, 1..=MAX_BRUSH_SIZE).text("Size"));
+                        if brush.shape == BrushShape::Spray {
+                            ui.add(egui::Slider::new(&mut brush.density, 0.01..=1.0).text("Density"));
+                        }
+                        let symmetry = &mut drawing.symmetry;
+                        egui::ComboBox::from_label("Symmetry")
+                            .selected_text(symmetry.mode.name())
+                            .show_ui(ui, |ui| {
+                                for mode in SymmetryMode::ALL {
+                                    ui.selectable_value(&mut symmetry.mode, mode, mode.name());
+                                }
+                            });
+                        if symmetry.mode != SymmetryMode::None {
+                            ui.horizontal(|ui| {
+                                ui.label("Center");
+                                ui.add(egui::DragValue::new(&mut symmetry.center.x).speed(0.5).fixed_decimals(1));
+                                ui.add(egui::DragValue::new(&mut symmetry.center.y).speed(0.5).fixed_decimals(1));
+                                let pick_text = if drawing.is_picking_symmetry_center { "Click the grid..." } else { "Pick" };
+                                if ui.button(pick_text).on_hover_text("Next click on the grid moves the center").clicked() {
+                                    drawing.is_picking_symmetry_center = !drawing.is_picking_symmetry_center;
+                                }
+                            });
+                        }
+                    }
+                    Tool::Select => {
+                        ui.label("Drag to select, right click or Esc to deselect");
+                        ui.horizontal(|ui| {
+                            let buttons = [
+                                ("Copy", "Ctrl+C", SelectionOperation::Copy),
+                                ("Cut", "Ctrl+X", SelectionOperation::Cut),
+                                ("Clear", "Delete", SelectionOperation::Clear),
+                                ("Invert", "Ctrl+I", SelectionOperation::Invert),
+                            ];
+                            for (text, shortcut, operation) in buttons {
+                                if ui.button(text).on_hover_text(shortcut).clicked() {
+                                    actions.push(GuiAction::EditSelection(operation));
+                                }
+                            }
+                            if ui.button("Paste").on_hover_text("Ctrl+V, then click to place").clicked() {
+                                actions.push(GuiAction::Paste);
+                            }
+                            if ui.button("Select all").on_hover_text("Ctrl+A").clicked() {
+                                actions.push(GuiAction::SelectAll);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Random fill").clicked() {
+                                actions.push(GuiAction::EditSelection(SelectionOperation::RandomFill));
+                            }
+                            ui.add(egui::Slider::new(&mut gui_state.fill_density, 0.0..=1.0).text("Density"));
+                        });
+                    }
+                    Tool::Fill => {
+                        ui.label("Click flips the connected region of dead or alive cells");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut drawing.fill_connectivity, Connectivity::Four, "4-connected");
+                            ui.radio_value(&mut drawing.fill_connectivity, Connectivity::Eight, "8-connected");
+                        });
+                        ui.add(
+                            egui::Slider::new(&mut drawing.max_fill_cells, 100..=10_000_000)
+                                .logarithmic(true)
+                                .text("Max cells"),
+                        )
+                        .on_hover_text("Bigger regions are not filled");
+                    }
+                    Tool::Shape(_) => {
+                        ui.label("Left drag draws, right drag erases, Esc cancels");
+                    }
+                }
+            });
+
+            ui.collapsing("Text", |ui| {
+                ui.add(egui::TextEdit::multiline(&mut gui_state.text).hint_text("Text to write in live cells").desired_rows(2));
+                let options = &mut gui_state.text_options;
+                ui.horizontal(|ui| {
+                    for font in TextFont::ALL {
+                        ui.radio_value(&mut options.font, font, font.name());
+                    }
+                });
+                ui.add(egui::Slider::new(&mut options.scale, 1..=MAX_TEXT_SCALE).text("Scale"));
+                ui.horizontal(|ui| {
+                    ui.label("Spacing");
+                    ui.add(egui::DragValue::new(&mut options.letter_spacing).range(0..=32)).on_hover_text("Between letters");
+                    ui.add(egui::DragValue::new(&mut options.line_spacing).range(0..=32)).on_hover_text("Between lines");
+                });
+                ui.checkbox(&mut options.outline, "Outline").on_hover_text("Only edges of the letters are alive");
+                if ui.button("Place").on_hover_text("Click the grid to stamp the text").clicked() {
+                    actions.push(GuiAction::ArmText);
+                }
+            });
+
+            ui.collapsing("Pattern file", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Path");
+                    ui.text_edit_singleline(&mut gui_state.pattern_path);
+                });
+                if ui.button("Import").on_hover_text("Place pattern from the file at the center of the screen").clicked() {
+                    actions.push(GuiAction::ImportPattern);
+                }
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("export_format")
+                        .selected_text(gui_state.export_format.name())
+                        .show_ui(ui, |ui| {
+                            let formats = PatternFormat::ALL.map(ExportFormat::Pattern);
+                            for format in formats.into_iter().chain([ExportFormat::Png]) {
+                                ui.selectable_value(&mut gui_state.export_format, format, format.name());
+                            }
+                        });
+                    egui::ComboBox::from_id_salt("export_region")
+                        .selected_text(gui_state.export_region.name())
+                        .show_ui(ui, |ui| {
+                            for region in [ExportRegion::AliveCells, ExportRegion::VisibleArea, ExportRegion::Selection] {
+                                ui.selectable_value(&mut gui_state.export_region, region, region.name());
+                            }
+                        });
+                    if ui.button("Export").on_hover_text("Save the region to the file").clicked() {
+                        actions.push(GuiAction::ExportPattern);
+                    }
+                });
+                if gui_state.export_format == ExportFormat::Png {
+                    egui::ComboBox::from_id_salt("image_palette")
+                        .selected_text(gui_state.image_palette.name())
+                        .show_ui(ui, |ui| {
+                            for palette in [ImagePalette::Monochrome, ImagePalette::Renderer] {
+                                ui.selectable_value(&mut gui_state.image_palette, palette, palette.name());
+                            }
+                        });
+                }
+                ui.collapsing("PNG import", |ui| {
+                    let options = &mut gui_state.image_options;
+                    ui.horizontal(|ui| {
+                        ui.label("Fit into");
+                        ui.add(egui::DragValue::new(&mut options.max_width).range(1..=16384));
+                        ui.add(egui::DragValue::new(&mut options.max_height).range(1..=16384));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut options.binarization, Binarization::Threshold, "Threshold");
+                        ui.radio_value(&mut options.binarization, Binarization::Dither, "Dither");
+                    });
+                    if options.binarization == Binarization::Threshold {
+                        ui.add(egui::Slider::new(&mut options.threshold, 0.0..=1.0).text("Threshold"));
+                    }
+                    ui.checkbox(&mut options.upscale, "Scale up small images");
+                    ui.checkbox(&mut options.invert, "Dark pixels are alive");
+                });
+                if let Some(window) = &mut gui_state.macrocell_window {
+                    ui.separator();
+                    ui.label(format!(
+                        "Macrocell: {}x{}, {} alive cells",
+                        window.content_size.0, window.content_size.1, window.alive_cells
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.label("Offset");
+                        ui.add(egui::DragValue::new(&mut window.x).range(0..=window.content_size.0));
+                        ui.add(egui::DragValue::new(&mut window.y).range(0..=window.content_size.1));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Size");
+                        ui.add(egui::DragValue::new(&mut window.width).range(1..=window.max_size.0));
+                        ui.add(egui::DragValue::new(&mut window.height).range(1..=window.max_size.1));
+                    });
+                    if ui.button("Place window").on_hover_text("Place this part of the pattern at the center of the screen").clicked() {
+                        actions.push(GuiAction::PlaceMacrocellWindow);
+                    }
+                }
+            });
+
+            ui.collapsing("Session", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Path");
+                    ui.text_edit_singleline(&mut gui_state.session_path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Save").on_hover_text("Save grid, generation, speed and camera (Ctrl+S)").clicked() {
+                        actions.push(GuiAction::SaveSession);
+                    }
+                    if ui.button("Load").on_hover_text("Restore saved session (Ctrl+O)").clicked() {
+                        actions.push(GuiAction::LoadSession);
+                    }
+                });
+            });
+
+            ui.collapsing("Recording", |ui| {
+                let recording = &mut gui_state.recording;
+                let is_recording = recording.progress.is_some();
+                ui.add_enabled_ui(!is_recording, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Path");
+                        ui.text_edit_singleline(&mut recording.path);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut recording.format, AnimationFormat::Gif, AnimationFormat::Gif.name());
+                        ui.radio_value(&mut recording.format, AnimationFormat::Apng, AnimationFormat::Apng.name());
+                        egui::ComboBox::from_id_salt("recording_palette")
+                            .selected_text(recording.palette.name())
+                            .show_ui(ui, |ui| {
+                                for palette in [ImagePalette::Monochrome, ImagePalette::Renderer] {
+                                    ui.selectable_value(&mut recording.palette, palette, palette.name());
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Region");
+                        for value in &mut recording.region {
+                            ui.add(egui::DragValue::new(value).range(0..=16384));
+                        }
+                        if ui.button("Visible area").clicked() {
+                            actions.push(GuiAction::RecordVisibleArea);
+                        }
+                    });
+                    ui.add(egui::Slider::new(&mut recording.every_generations, 1..=100).text("Every N generations").logarithmic(true));
+                    ui.add(egui::Slider::new(&mut recording.scale, 1..=16).text("Scale"));
+                    ui.add(egui::Slider::new(&mut recording.frame_delay_ms, 10..=2000).text("Frame delay (ms)").logarithmic(true));
+                });
+                match &recording.progress {
+                    Some(progress) => {
+                        ui.label(format!("Recording: {}", progress));
+                        if ui.button("Stop").clicked() {
+                            actions.push(GuiAction::StopRecording);
+                        }
+                    }
+                    None => {
+                        if ui.button("Start").clicked() {
+                            actions.push(GuiAction::StartRecording);
+                        }
+                    }
+                }
+            });
+
+            ui.collapsing("Screenshot", |ui| {
+                let screenshot = &mut gui_state.screenshot;
+                ui.horizontal(|ui| {
+                    ui.label("Path");
+                    ui.text_edit_singleline(&mut screenshot.path);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    ui.add(egui::DragValue::new(&mut screenshot.width).range(1..=MAX_SCREENSHOT_SIZE));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut screenshot.height).range(1..=MAX_SCREENSHOT_SIZE));
+                    for (name, width, height) in [("4K", 3840, 2160), ("8K", 7680, 4320)] {
+                        if ui.small_button(name).clicked() {
+                            (screenshot.width, screenshot.height) = (width, height);
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Save image").on_hover_text("Cells only, at the size above (F12)").clicked() {
+                        actions.push(GuiAction::Screenshot(ScreenshotKind::Simulation));
+                    }
+                    if ui.button("Save window").on_hover_text("With GUI, at the window size (Shift+F12)").clicked() {
+                        actions.push(GuiAction::Screenshot(ScreenshotKind::Window));
+                    }
+                });
+            });
+
+            ui.collapsing("Shaders", |ui| {
+                let shaders = &mut gui_state.shaders;
+                ui.checkbox(&mut shaders.is_watching, "Reload from disk")
+                    .on_hover_text("Files are checked twice a second, a shader that doesn't compile keeps the last working one");
+                for kind in ShaderKind::ALL {
+                    ui.horizontal(|ui| {
+                        ui.label(kind.name());
+                        ui.text_edit_singleline(shaders.get_path_mut(kind));
+                    });
+                    if let Some(error) = shaders.get_error(kind) {
+                        let text = egui::RichText::new(error).monospace().color(ui.visuals().error_fg_color);
+                        egui::ScrollArea::vertical()
+                            .id_salt(kind.name())
+                            .max_height(160.0)
+                            .show(ui, |ui| ui.label(text));
+                    }
+                }
+                if ui.button("Use built-in").clicked() {
+                    actions.push(GuiAction::ResetShaders);
+                }
+            });
+
+            if let Some(status) = &gui_state.status {
+                ui.label(status);
+            }
         });
-}
\ No newline at end of file
+    add_library_window(ui, library, &mut gui_state.library, gui_state.theme.get_theme(), drawing, &mut actions);
+    add_viewport_frames(ui, &mut gui_state.view);
+    actions
+}
+
+/// Outlines every view when there's more than one, and adds handles to move and resize picture in picture views
+fn add_viewport_frames(ctx: &Context, view: &mut ViewGui) {
+    let settings = &mut view.viewports;
+    if settings.get_count() == 1 {
+        return;
+    }
+    let screen = ctx.screen_rect();
+    let pixels_per_point = ctx.pixels_per_point();
+    let window_size = (screen.size() * pixels_per_point).round();
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (index, rect) in settings.get_rects(PhysicalSize::new(window_size.x as u32, window_size.y as u32)).into_iter().enumerate() {
+        let [x, y, width, height] = rect.map(|value| value as f32 / pixels_per_point);
+        let stroke = if index == view.focused_viewport {
+            egui::Stroke::new(2.0, FOCUSED_VIEW_COLOR)
+        } else {
+            egui::Stroke::new(1.0, VIEW_BORDER_COLOR)
+        };
+        let rect = egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(width, height));
+        painter.rect_stroke(rect.shrink(stroke.width / 2.0), 0.0, stroke);
+    }
+
+    if settings.layout != ViewportLayout::PictureInPicture {
+        return;
+    }
+    for (index, inset) in settings.insets.iter_mut().take(MAX_VIEWPORTS - 1).enumerate() {
+        let [x, y, width, height] = *inset;
+        let min = screen.min + egui::vec2(x, y) * screen.size();
+        let max = min + egui::vec2(width, height) * screen.size();
+        // drag deltas as fractions of the window
+        let mut moved = egui::Vec2::ZERO;
+        let mut resized = egui::Vec2::ZERO;
+        egui::Area::new(egui::Id::new(("viewport_move", index)))
+            .fixed_pos(min)
+            .movable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let label = egui::Label::new(format!("View {}", index + 2)).selectable(false).sense(egui::Sense::drag());
+                    moved = ui.add(label).on_hover_cursor(egui::CursorIcon::Grab).drag_delta() / screen.size();
+                });
+            });
+        egui::Area::new(egui::Id::new(("viewport_resize", index)))
+            .fixed_pos(max)
+            .pivot(Align2::RIGHT_BOTTOM)
+            .movable(false)
+            .show(ctx, |ui| {
+                let (rect, response) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::drag());
+                let corner = vec![rect.right_top(), rect.right_bottom(), rect.left_bottom()];
+                ui.painter().add(egui::Shape::convex_polygon(corner, FOCUSED_VIEW_COLOR, egui::Stroke::NONE));
+                resized = response.on_hover_cursor(egui::CursorIcon::ResizeNwSe).drag_delta() / screen.size();
+            });
+        *inset = ViewportSettings::clamp_inset([x + moved.x, y + moved.y, width + resized.x, height + resized.y]);
+    }
+}
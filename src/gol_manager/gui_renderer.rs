@@ -57,6 +57,7 @@ impl EguiRenderer {
         event_response.consumed
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &mut self,
         device: &wgpu::Device,
@@ -64,32 +65,34 @@ impl EguiRenderer {
         encoder: &mut wgpu::CommandEncoder,
         window: &Window,
         window_surface_view: &wgpu::TextureView,
+        // also gets the same GUI, e.g. a copy of the frame for a screenshot
+        extra_view: Option<&wgpu::TextureView>,
         screen_descriptor: ScreenDescriptor,
         mut run_ui: impl FnMut(&Context),
     ) {
         // self.state.set_pixels_per_point(window.scale_factor() as f32);
-        let raw_input = self.state.take_egui_input(&window);
-        let full_output = self.context.run(raw_input, |ui| {
+        let raw_input = self.state.take_egui_input(window);
+        let full_output = self.context.run(raw_input, |_ui| {
             run_ui(&self.context);
         });
 
         self.state
-            .handle_platform_output(&window, full_output.platform_output);
+            .handle_platform_output(window, full_output.platform_output);
 
         let tris = self
             .context
             .tessellate(full_output.shapes, full_output.pixels_per_point);
         for (id, image_delta) in &full_output.textures_delta.set {
             self.renderer
-                .update_texture(&device, &queue, *id, image_delta);
+                .update_texture(device, queue, *id, image_delta);
         }
         self.renderer
-            .update_buffers(&device, &queue, encoder, &tris, &screen_descriptor);
+            .update_buffers(device, queue, encoder, &tris, &screen_descriptor);
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        for view in std::iter::once(window_surface_view).chain(extra_view) {
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &window_surface_view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
@@ -0,0 +1,155 @@
+use egui::{Context, TextureHandle};
+use std::collections::HashMap;
+use crate::drawing::{GoLDrawing, StampMode};
+use crate::gol_renderer::Theme;
+use crate::gol_manager::gui_adder::GuiAction;
+use crate::gol_manager::patterns::Pattern;
+use crate::pattern_library::PatternLibrary;
+
+/// Thumbnails are drawn into THUMBNAIL_SIZE x THUMBNAIL_SIZE square, bigger patterns are scaled down
+const THUMBNAIL_SIZE: u32 = 48;
+
+pub struct LibraryGui {
+    pub is_open: bool,
+    pub query: String,
+    /// Only patterns with this tag are shown
+    pub tag: Option<String>,
+    pub new_directory: String,
+    /// Entry that will be placed with the next click, GoLManager resets it when placement is canceled
+    pub armed_entry: Option<usize>,
+    /// Keyed by library entry index, GoLManager clears it when the library is rescanned or the theme changes
+    pub thumbnails: HashMap<usize, TextureHandle>,
+}
+
+impl LibraryGui {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            query: String::new(),
+            tag: None,
+            new_directory: String::new(),
+            armed_entry: None,
+            thumbnails: HashMap::new(),
+        }
+    }
+}
+
+pub fn add_library_window(
+    ctx: &Context,
+    library: &PatternLibrary,
+    library_gui: &mut LibraryGui,
+    theme: &Theme,
+    drawing: &mut GoLDrawing,
+    actions: &mut Vec<GuiAction>,
+) {
+    let mut is_open = library_gui.is_open;
+    egui::Window::new("Pattern library")
+        .open(&mut is_open)
+        .default_width(320.0)
+        .show(ctx, |ui| {
+            ui.add(egui::TextEdit::singleline(&mut library_gui.query).hint_text("Search"));
+            ui.horizontal_wrapped(|ui| {
+                for tag in library.tags() {
+                    let is_selected = library_gui.tag.as_deref() == Some(tag);
+                    if ui.selectable_label(is_selected, tag).clicked() {
+                        library_gui.tag = if is_selected { None } else { Some(tag.to_string()) };
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Stamp mode");
+                for mode in StampMode::ALL {
+                    ui.radio_value(&mut drawing.stamp_mode, mode, mode.name());
+                }
+            });
+            match library_gui.armed_entry.and_then(|index| library.entries().get(index)) {
+                Some(entry) => ui.label(format!(
+                    "Placing {}: left click to stamp, R to rotate, F/V to flip, right click or Esc to stop",
+                    entry.name
+                )),
+                None => ui.label("Click a pattern to place it"),
+            };
+            ui.separator();
+
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                let visible_entries = library.entries().iter().enumerate().filter(|(_, entry)| {
+                    entry.matches(&library_gui.query)
+                        && library_gui.tag.as_ref().is_none_or(|tag| entry.tags.contains(tag))
+                });
+                for (index, entry) in visible_entries {
+                    let thumbnail = library_gui.thumbnails.entry(index).or_insert_with(|| {
+                        ctx.load_texture(format!("library_thumbnail_{}", index), get_thumbnail(&entry.pattern, theme), egui::TextureOptions::NEAREST)
+                    });
+                    let is_armed = library_gui.armed_entry == Some(index);
+                    ui.horizontal(|ui| {
+                        let [width, height] = thumbnail.size();
+                        let scale = THUMBNAIL_SIZE as f32 / width.max(height) as f32;
+                        let image = egui::Image::new(&*thumbnail).fit_to_exact_size(egui::vec2(width as f32, height as f32) * scale);
+                        // same width for all thumbnails, so names are aligned
+                        let button_size = egui::Vec2::splat(THUMBNAIL_SIZE as f32) + ui.spacing().button_padding * 2.0;
+                        let image_clicked = ui.add_sized(button_size, egui::ImageButton::new(image).selected(is_armed)).clicked();
+                        ui.vertical(|ui| {
+                            let name_clicked = ui.selectable_label(is_armed, egui::RichText::new(&entry.name).strong()).clicked();
+                            ui.label(format!("{}x{}", entry.pattern.width, entry.pattern.height));
+                            ui.small(entry.tags.join(", "));
+                            if image_clicked || name_clicked {
+                                actions.push(GuiAction::ArmLibraryPattern(index));
+                            }
+                        });
+                    });
+                }
+            });
+
+            ui.collapsing("Directories", |ui| {
+                for directory in library.directories() {
+                    ui.horizontal(|ui| {
+                        ui.label(directory.display().to_string());
+                        if ui.small_button("Remove").clicked() {
+                            actions.push(GuiAction::RemoveLibraryDirectory(directory.clone()));
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut library_gui.new_directory);
+                    if ui.button("Add").on_hover_text("Load .rle, .cells and .png files from the directory and its subdirectories").clicked() {
+                        actions.push(GuiAction::AddLibraryDirectory);
+                    }
+                });
+                if ui.button("Rescan").on_hover_text("Reload all directories").clicked() {
+                    actions.push(GuiAction::RescanLibrary);
+                }
+                if !library.errors().is_empty() {
+                    let errors: Vec<String> = library
+                        .errors()
+                        .iter()
+                        .map(|(path, err)| format!("{}: {}", path.display(), err))
+                        .collect();
+                    ui.label(format!("{} files failed to load", errors.len())).on_hover_text(errors.join("\n"));
+                }
+            });
+        });
+    library_gui.is_open = is_open;
+}
+
+/// Each thumbnail pixel is the share of alive cells it covers, in theme colors
+fn get_thumbnail(pattern: &Pattern, theme: &Theme) -> egui::ColorImage {
+    let cells_per_pixel = pattern.width.max(pattern.height).div_ceil(THUMBNAIL_SIZE).max(1);
+    let width = pattern.width.div_ceil(cells_per_pixel).max(1);
+    let height = pattern.height.div_ceil(cells_per_pixel).max(1);
+    let colors = theme.get_srgb_colors();
+    let (dead, alive) = (colors[0], colors[1]);
+    let mut alive_counts = vec![0u32; (width * height) as usize];
+    for (x, y) in pattern.alive_cells() {
+        alive_counts[((y / cells_per_pixel) * width + x / cells_per_pixel) as usize] += 1;
+    }
+    let pixels = alive_counts
+        .iter()
+        .flat_map(|count| {
+            let share = *count as f32 / (cells_per_pixel * cells_per_pixel) as f32;
+            // square root keeps sparse areas of big patterns visible
+            let t = share.sqrt();
+            [0, 1, 2].map(|i| (dead[i] as f32 + (alive[i] as f32 - dead[i] as f32) * t).round() as u8)
+        })
+        .collect::<Vec<u8>>();
+    egui::ColorImage::from_rgb([width as usize, height as usize], &pixels)
+}
@@ -1,25 +1,66 @@
 mod gui_renderer;
 mod gui_adder;
 mod camera;
+mod viewports;
+mod library_gui;
+pub mod patterns;
 
-use crate::drawing::GoLDrawing;
-use crate::gol::GoL;
-use crate::gol_manager::camera::{Camera, CameraController};
-use crate::gol_renderer::GoLRenderer;
+use crate::clipboard::Clipboard;
+use crate::drawing::{render_text, GoLDrawing};
+use crate::gol::{self, GoL};
+use crate::gol_manager::camera::{wrap_angle, Camera, CameraController, PixelGrid, ViewBookmark};
+use crate::gol_manager::viewports::Viewports;
+use crate::history::History;
+use crate::gol_renderer::{
+    self, load_all_themes, read_texture, save_themes, GoLRenderer, LineOverlay, MinimapView, RectOverlay, DEFAULT_THEMES_PATH, GUIDE_COLOR,
+    SELECTION_BORDER_COLOR, SELECTION_FILL_COLOR,
+};
+use crate::pattern_io::{self, Macrocell, PatternError, PatternFormat};
+use crate::pattern_library::PatternLibrary;
 use crate::perf_monitor::PerfMonitor;
+use crate::recorder::{Recorder, RecordingSettings};
+use crate::screenshot::{self, ScreenshotKind};
+use crate::shader_reload::{ShaderKind, ShaderWatcher};
+use crate::tracker::Tracker;
+use crate::session::{Session, SessionError, CONWAY_RULE};
 use egui_wgpu::wgpu;
-use glam::{vec2, Mat3};
-use gui_adder::add_gui;
+use glam::{vec2, Mat3, Vec2};
+use gui_adder::{add_gui, ExportFormat, ExportRegion, GuiAction, GuiState, MacrocellWindow, SelectionOperation};
 use gui_renderer::EguiRenderer;
+use patterns::Pattern;
+use rand::Rng;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
-use winit::event::{ElementState, WindowEvent};
-use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
 
-pub struct GoLKeyboardController {}
+/// Key N recalls bookmark N, Ctrl+N saves it
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+pub struct GoLKeyboardController {
+    modifiers: ModifiersState,
+    /// Shortcuts that do the same thing as GUI buttons, GoLManager handles them together with GUI actions
+    actions: Vec<GuiAction>,
+}
 
 impl GoLKeyboardController {
-    pub fn handle_input(&self, event: &WindowEvent, gol_config: &mut GoLConfig) -> bool {
+    pub fn handle_input(&mut self, event: &WindowEvent, gol_config: &mut GoLConfig) -> bool {
+        if let WindowEvent::ModifiersChanged(modifiers) = event {
+            self.modifiers = modifiers.state();
+            return false;
+        }
         if let WindowEvent::KeyboardInput { event, .. } = event {
             if let PhysicalKey::Code(keycode) = event.physical_key {
                 if event.state == ElementState::Pressed && !event.repeat {
@@ -28,6 +69,83 @@ impl GoLKeyboardController {
                             gol_config.is_paused = !gol_config.is_paused;
                             true
                         }
+                        KeyCode::KeyS if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::SaveSession);
+                            true
+                        }
+                        KeyCode::KeyO if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::LoadSession);
+                            true
+                        }
+                        KeyCode::KeyC if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::EditSelection(SelectionOperation::Copy));
+                            true
+                        }
+                        KeyCode::KeyX if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::EditSelection(SelectionOperation::Cut));
+                            true
+                        }
+                        KeyCode::KeyI if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::EditSelection(SelectionOperation::Invert));
+                            true
+                        }
+                        KeyCode::Delete => {
+                            self.actions.push(GuiAction::EditSelection(SelectionOperation::Clear));
+                            true
+                        }
+                        KeyCode::KeyV if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::Paste);
+                            true
+                        }
+                        KeyCode::KeyA if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::SelectAll);
+                            true
+                        }
+                        KeyCode::KeyZ if self.modifiers.control_key() && self.modifiers.shift_key() => {
+                            self.actions.push(GuiAction::Redo);
+                            true
+                        }
+                        KeyCode::KeyZ if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::Undo);
+                            true
+                        }
+                        KeyCode::KeyY if self.modifiers.control_key() => {
+                            self.actions.push(GuiAction::Redo);
+                            true
+                        }
+                        KeyCode::Home => {
+                            self.actions.push(GuiAction::FitToContent);
+                            true
+                        }
+                        KeyCode::KeyT => {
+                            self.actions.push(GuiAction::ToggleTracking);
+                            true
+                        }
+                        KeyCode::KeyN => {
+                            self.actions.push(GuiAction::Step);
+                            true
+                        }
+                        KeyCode::F12 => {
+                            self.actions.push(GuiAction::Screenshot(if self.modifiers.shift_key() {
+                                ScreenshotKind::Window
+                            } else {
+                                ScreenshotKind::Simulation
+                            }));
+                            true
+                        }
+                        KeyCode::KeyG => {
+                            self.actions.push(GuiAction::ToggleGrid);
+                            true
+                        }
+                        _ if BOOKMARK_KEYS.contains(&keycode) => {
+                            let index = BOOKMARK_KEYS.iter().position(|key| *key == keycode).unwrap();
+                            self.actions.push(if self.modifiers.control_key() {
+                                GuiAction::SaveBookmark(Some(index))
+                            } else {
+                                GuiAction::GoToBookmark(index)
+                            });
+                            true
+                        }
                         _ => false,
                     };
                 }
@@ -36,8 +154,14 @@ impl GoLKeyboardController {
         }
         false
     }
+    pub fn take_actions(&mut self) -> Vec<GuiAction> {
+        std::mem::take(&mut self.actions)
+    }
     pub fn new() -> Self {
-        Self {}
+        Self {
+            modifiers: ModifiersState::empty(),
+            actions: Vec::new(),
+        }
     }
 }
 
@@ -56,11 +180,37 @@ pub struct GoLManager {
     render_quad_transform: Mat3,
     gol: GoL,
     renderer: GoLRenderer,
-    camera: Camera,
-    camera_controller: CameraController,
+    viewports: Viewports,
     keyboard_controller: GoLKeyboardController,
     drawing: GoLDrawing,
     gui_renderer: EguiRenderer,
+    gui_state: GuiState,
+    /// Last imported macrocell pattern, windows of it can be placed from GUI
+    macrocell: Option<Macrocell>,
+    library: PatternLibrary,
+    clipboard: Clipboard,
+    /// All grid edits go through it, so they can be undone
+    history: History,
+    /// Kept after recording is stopped until the encoder writes the file
+    recorder: Option<Recorder>,
+    /// Object the camera follows
+    tracker: Option<Tracker>,
+    /// View whose camera follows the tracked object, the focused one when tracking started
+    tracked_viewport: usize,
+    /// Taken at the start of the next update (simulation) or frame (window), not in the middle of a frame
+    screenshot_request: Option<ScreenshotKind>,
+    /// Window frame copy rendered together with the frame, read back in the next update
+    pending_window_screenshot: Option<(PathBuf, wgpu::Texture)>,
+    /// PNG encoding, result is the status message
+    screenshot_thread: Option<JoinHandle<String>>,
+    render_target_format: wgpu::TextureFormat,
+    /// Some while shaders are reloaded from disk
+    shader_watcher: Option<ShaderWatcher>,
+    /// Last custom rule that compiled, it's applied to grids created later (e.g. by loading a session)
+    simulation_shader: Option<String>,
+    /// Physical pixels, needed to tell if a click is on the minimap
+    cursor_position: Vec2,
+    is_dragging_minimap: bool,
     perf_monitor: PerfMonitor,
     time_accumulator: Duration,
     last_update: Instant,
@@ -75,12 +225,11 @@ impl GoLManager {
         window: Arc<winit::window::Window>,
         render_target_format: wgpu::TextureFormat,
     ) -> Self {
-        let mut camera = Camera::new(aspect_ratio);
-        let camera_controller = CameraController::new(0.05);
+        let viewports = Viewports::new(aspect_ratio);
 
         let game_width = 2000;
         let game_height = 2000;
-        let gol = GoL::new(&device, game_width, game_height);
+        let gol = GoL::new(device, game_width, game_height);
         let state: Vec<u8> = (0..game_width * game_height)
             .map(|i| {
                 if i < game_width * game_height / 2 {
@@ -90,21 +239,14 @@ impl GoLManager {
                 }
             })
             .collect();
-        gol.write_area(&queue, &state, 0, 0, game_width, game_height);
+        gol.write_area(queue, &state, 0, 0, game_width, game_height);
 
-        let renderer = GoLRenderer::new(&device, render_target_format);
+        let renderer = GoLRenderer::new(device, render_target_format);
 
-        let baseline_size = 500;
-        let gol_size_tuple = gol.get_size();
-        let gol_size = vec2(gol_size_tuple.0 as f32, gol_size_tuple.1 as f32);
-        let scale = vec2(
-            gol_size.x / baseline_size as f32,
-            gol_size.y / baseline_size as f32,
-        );
-        let render_quad_transform = Mat3::from_scale(scale);
+        let render_quad_transform = Self::get_render_quad_transform(&gol);
 
         let gui_renderer = EguiRenderer::new(
-            &device,
+            device,
             render_target_format,
             None,
             1,
@@ -115,6 +257,15 @@ impl GoLManager {
         let mut perf_monitor = PerfMonitor::new();
         perf_monitor.start("update");
 
+        let mut drawing = GoLDrawing::new();
+        drawing.symmetry.center = vec2(game_width as f32, game_height as f32) / 2.0;
+
+        let mut gui_state = GuiState::new();
+        match load_all_themes(Path::new(DEFAULT_THEMES_PATH)) {
+            Ok(themes) => gui_state.theme.themes = themes,
+            Err(err) => gui_state.status = Some(format!("Can't load themes: {}", err)),
+        }
+
         Self {
             config: GoLConfig {
                 is_paused: false,
@@ -123,19 +274,49 @@ impl GoLManager {
             render_quad_transform,
             gol,
             renderer,
-            camera,
-            camera_controller,
+            viewports,
             keyboard_controller: GoLKeyboardController::new(),
-            drawing: GoLDrawing::new(),
+            drawing,
             gui_renderer,
+            gui_state,
+            macrocell: None,
+            library: PatternLibrary::new(),
+            clipboard: Clipboard::new(),
+            history: History::new(),
+            recorder: None,
+            tracker: None,
+            tracked_viewport: 0,
+            screenshot_request: None,
+            pending_window_screenshot: None,
+            screenshot_thread: None,
+            render_target_format,
+            shader_watcher: None,
+            simulation_shader: None,
+            cursor_position: Vec2::ZERO,
+            is_dragging_minimap: false,
             perf_monitor,
             time_accumulator: Duration::from_secs(0),
             last_update: Instant::now(),
             max_ms_per_update: Duration::from_millis(50),
         }
     }
+    /// Quad is scaled with the grid, so cells have the same size on the screen for any grid size
+    fn get_render_quad_transform(gol: &GoL) -> Mat3 {
+        let baseline_size = 500;
+        let gol_size_tuple = gol.get_size();
+        let gol_size = vec2(gol_size_tuple.0 as f32, gol_size_tuple.1 as f32);
+        let scale = vec2(
+            gol_size.x / baseline_size as f32,
+            gol_size.y / baseline_size as f32,
+        );
+        Mat3::from_scale(scale)
+    }
+
     pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        self.camera_controller.update_camera(&mut self.camera);
+        self.update_shaders(device);
+        for viewport in self.viewports.get_all_mut() {
+            viewport.camera_controller.update_camera(&mut viewport.camera);
+        }
 
         // don't update if last update took too long. This is to prevent snowballing updates.
         // For example, if simulation can't keep up update takes too long -> next update would take even longer (since last update took longer and more updates are queued up)
@@ -145,35 +326,184 @@ impl GoLManager {
 
             while self.time_accumulator >= self.config.get_update_interval() {
                 self.time_accumulator -= self.config.get_update_interval();
-                self.gol.update(device, queue);
+                self.advance_generation(device, queue);
             }
         } else {
             self.last_update = Instant::now();
             self.time_accumulator = Duration::from_secs(0);
         }
+
+        self.update_recorder(device);
+        self.update_tracker(device);
+        self.update_screenshot(device, queue);
+    }
+
+    /// One generation together with everything that follows the simulation
+    fn advance_generation(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.history.before_generation_advance(&self.gol, device, queue);
+        self.gol.update(device, queue);
+        self.renderer.update_trail(device, queue, &self.gol);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.on_generation(&self.gol, device, queue);
+        }
+        if let Some(tracker) = &mut self.tracker {
+            tracker.on_generation(&self.gol, device, queue);
+        }
+    }
+    fn update_recorder(&mut self, device: &wgpu::Device) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        recorder.poll(device);
+        self.gui_state.recording.progress = recorder.is_recording().then(|| recorder.get_status());
+        if recorder.is_finished() {
+            let status = recorder.get_status();
+            self.gui_state.status = Some(match recorder.take_result() {
+                Ok(frames) => format!("Recording saved, {} frames written ({})", frames, status),
+                Err(err) => format!("Recording failed: {}", err),
+            });
+            self.recorder = None;
+        }
+    }
+    fn update_screenshot(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.screenshot_thread.as_ref().is_some_and(|thread| thread.is_finished()) {
+            let status = self.screenshot_thread.take().unwrap().join();
+            self.gui_state.status = Some(status.unwrap_or_else(|_| "Can't save screenshot".to_string()));
+        }
+        if let Some((path, texture)) = self.pending_window_screenshot.take() {
+            let pixels = pollster::block_on(read_texture(device, queue, &texture));
+            self.save_screenshot(path, (texture.width(), texture.height()), pixels);
+        }
+        if self.screenshot_request == Some(ScreenshotKind::Simulation) {
+            self.screenshot_request = None;
+            let settings = &self.gui_state.screenshot;
+            let (path, size) = (PathBuf::from(&settings.path), (settings.width, settings.height));
+            let camera = Camera {
+                aspect_ratio: size.0 as f32 / size.1 as f32,
+                ..self.viewports.get_focused().camera
+            };
+            let pixels = pollster::block_on(self.renderer.render_image(
+                device,
+                queue,
+                &self.gol,
+                camera.get_matrix(),
+                self.render_quad_transform,
+                size,
+            ));
+            self.save_screenshot(path, size, pixels);
+        }
+    }
+    fn update_shaders(&mut self, device: &wgpu::Device) {
+        let shaders = &self.gui_state.shaders;
+        if !shaders.is_watching {
+            self.shader_watcher = None;
+            return;
+        }
+        let paths = ShaderKind::ALL.map(|kind| (kind, PathBuf::from(shaders.get_path(kind))));
+        // edited paths are watched from scratch, so the new files are loaded right away
+        let is_outdated = |watcher: &ShaderWatcher| paths.iter().any(|(kind, path)| watcher.get_path(*kind) != Some(path));
+        if self.shader_watcher.as_ref().is_none_or(is_outdated) {
+            self.shader_watcher = Some(ShaderWatcher::new(paths));
+        }
+        for (kind, source) in self.shader_watcher.as_mut().unwrap().poll() {
+            let result = source.and_then(|source| match kind {
+                ShaderKind::Simulation => {
+                    self.gol.set_shader(device, &source)?;
+                    self.simulation_shader = Some(source);
+                    Ok(())
+                }
+                ShaderKind::Render => self.renderer.set_shader(device, &source),
+            });
+            self.gui_state.status = Some(match result {
+                Ok(()) => format!("{} shader reloaded", kind.name()),
+                Err(_) => format!("{} shader failed, the last working one is still used", kind.name()),
+            });
+            self.gui_state.shaders.set_error(kind, result.err());
+        }
+    }
+    fn save_screenshot(&mut self, path: PathBuf, (width, height): (u32, u32), pixels: Vec<u8>) {
+        self.gui_state.status = Some(format!("Saving screenshot to {}...", path.display()));
+        self.screenshot_thread = Some(screenshot::save_png_in_background(path, width, height, pixels));
+    }
+    fn update_tracker(&mut self, device: &wgpu::Device) {
+        let Some(tracker) = &mut self.tracker else {
+            return;
+        };
+        tracker.poll(device);
+        self.gui_state.view.tracking = Some(tracker.get_status());
+        // camera stays where the object was last seen
+        let target = (!tracker.is_lost()).then(|| tracker.get_center());
+        let target = target.map(|cell| self.cell_to_world(cell));
+        if let Some(viewport) = self.viewports.get_mut(self.tracked_viewport) {
+            viewport.camera_controller.set_follow_target(target);
+        }
     }
     pub fn handle_input(
         &mut self,
         event: &WindowEvent,
         window: Arc<winit::window::Window>,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> bool {
-        self.gui_renderer.handle_input(&window, event)
-            || self.keyboard_controller.handle_input(event, &mut self.config)
-            || self.camera_controller.handle_input(event)
+        if self.gui_renderer.handle_input(&window, event) {
+            // drag that started in a view can end over the GUI
+            if matches!(event, WindowEvent::MouseInput { state: ElementState::Released, .. }) {
+                self.viewports.route_input(event);
+            }
+            return true;
+        }
+        // cursor relative to the view under it, the window one is still needed for the minimap
+        let view_event = self.viewports.route_input(event);
+        if self.keyboard_controller.handle_input(event, &mut self.config) || self.handle_minimap_input(event, &window) {
+            return true;
+        }
+        let viewport = self.viewports.get_hovered_mut();
+        viewport.camera_controller.handle_input(&view_event, viewport.get_size(), &viewport.camera)
             || self.drawing.handle_input(
-                event,
-                window.clone(),
+                &view_event,
+                viewport.get_size(),
                 &self.gol,
-                self.camera.get_matrix(),
+                viewport.camera.get_matrix(),
                 self.render_quad_transform,
+                device,
                 queue,
+                &mut self.history,
             )
     }
-    pub fn handle_aspect_ratio_change(&mut self, new_aspect_ratio: f32) {
-        self.camera.aspect_ratio = new_aspect_ratio;
+    /// Clicking or dragging on the minimap centers the camera on that point
+    fn handle_minimap_input(&mut self, event: &WindowEvent, window: &winit::window::Window) -> bool {
+        let screen_size = (window.inner_size().width, window.inner_size().height);
+        let grid_size = self.gol.get_size();
+        let is_on_minimap = self.gui_state.view.minimap.contains(screen_size, grid_size, self.cursor_position);
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = vec2(position.x as f32, position.y as f32);
+                if !self.is_dragging_minimap {
+                    return false;
+                }
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. }
+                if is_on_minimap =>
+            {
+                self.is_dragging_minimap = true;
+                self.stop_tracking();
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. }
+                if self.is_dragging_minimap =>
+            {
+                self.is_dragging_minimap = false;
+                return true;
+            }
+            _ => return false,
+        }
+        if let Some(cell) = self.gui_state.view.minimap.screen_to_cell(screen_size, grid_size, self.cursor_position) {
+            let position = self.cell_to_world(cell);
+            let viewport = self.viewports.get_focused_mut();
+            viewport.camera_controller.move_to(&mut viewport.camera, position);
+        }
+        // other controllers still need to know where the cursor is
+        !matches!(event, WindowEvent::CursorMoved { .. })
     }
-
     pub fn render(
         &mut self,
         device: &wgpu::Device,
@@ -183,22 +513,108 @@ impl GoLManager {
         window: &winit::window::Window,
     ) {
         self.perf_monitor.start_frame();
-        self.renderer.rerender(
-            &device,
-            &queue,
-            encoder,
+        self.viewports.update_layout(&self.gui_state.view.viewports, window.inner_size());
+        self.gui_state.view.focused_viewport = self.viewports.get_focused_index();
+        let mut gui_actions = Vec::new();
+        if self.drawing.get_armed_pattern().is_none() {
+            self.gui_state.library.armed_entry = None;
+        }
+        self.drawing.flush_stroke(device, queue, &self.gol, &mut self.history);
+        self.gui_state.undo_count = self.history.get_undo_count();
+        self.gui_state.view.rotation_degrees = self.viewports.get_focused().camera.rotation.to_degrees();
+        self.gui_state.redo_count = self.history.get_redo_count();
+        if let Some(status) = self.drawing.take_status() {
+            self.gui_state.status = Some(status);
+        }
+        if self.drawing.take_preview_changed() {
+            self.renderer.set_ghost_pattern(device, queue, self.drawing.get_preview_pattern());
+        }
+        let hovered = self.viewports.get_hovered();
+        let preview_position = self.drawing.get_preview_position(
+            hovered.get_size(),
             &self.gol,
-            &target_view,
-            self.camera.get_matrix(),
+            hovered.camera.get_matrix(),
             self.render_quad_transform,
         );
+        self.renderer.set_ghost_position(preview_position);
+        let selection_overlay = self.drawing.get_selection(&self.gol).map(|(x, y, width, height)| RectOverlay {
+            rect: [x as f32, y as f32, width as f32, height as f32],
+            fill_color: SELECTION_FILL_COLOR,
+            border_color: SELECTION_BORDER_COLOR,
+        });
+        let symmetry = &self.drawing.symmetry;
+        let guides: Vec<LineOverlay> = symmetry
+            .guides()
+            .into_iter()
+            .map(|(point, direction)| LineOverlay {
+                point: point.to_array(),
+                direction: direction.to_array(),
+                color: GUIDE_COLOR,
+            })
+            .collect();
+        // small square marks the center, lines alone don't show it for single mirror
+        let center_overlay = (!guides.is_empty()).then(|| {
+            let center = symmetry.get_center();
+            RectOverlay {
+                rect: [center.x - 0.5, center.y - 0.5, 1.0, 1.0],
+                fill_color: [0.0; 4],
+                border_color: GUIDE_COLOR,
+            }
+        });
+        self.renderer.set_rect_overlays(selection_overlay.into_iter().chain(center_overlay).collect());
+        self.renderer.set_line_overlays(guides);
+        self.renderer.set_grid(&self.gui_state.view.grid);
+        self.renderer.set_trail(&self.gui_state.view.trail);
+        self.renderer.set_diff(self.gui_state.view.is_showing_diff);
+        let pixel_grid = self.gui_state.view.is_pixel_snapping.then(|| {
+            let corner = self.cell_to_world(Vec2::ZERO);
+            PixelGrid {
+                corner,
+                cell_size: self.cell_to_world(Vec2::X).x - corner.x,
+            }
+        });
+        for viewport in self.viewports.get_all_mut() {
+            let size = viewport.get_size();
+            viewport.camera_controller.set_pixel_snapping(pixel_grid, size);
+        }
+        if self.renderer.set_theme(queue, self.gui_state.theme.get_theme()) {
+            self.gui_state.library.thumbnails.clear();
+        }
+        // shared by all viewports and the screenshot pass below
+        self.renderer.update_density(device, queue, &self.gol);
+        self.render_viewports(device, queue, encoder, target_view, window);
+        // same frame drawn once more into a texture that can be copied, the surface usually can't
+        let screenshot_texture = (self.screenshot_request == Some(ScreenshotKind::Window)).then(|| {
+            self.screenshot_request = None;
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Window Screenshot"),
+                size: wgpu::Extent3d {
+                    width: window.inner_size().width,
+                    height: window.inner_size().height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.render_target_format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        });
+        let screenshot_view = screenshot_texture
+            .as_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        if let Some(view) = &screenshot_view {
+            self.render_viewports(device, queue, encoder, view, window);
+        }
 
         self.gui_renderer.draw(
             device,
             queue,
             encoder,
             window,
-            &target_view,
+            target_view,
+            screenshot_view.as_ref(),
             egui_wgpu::ScreenDescriptor {
                 size_in_pixels: [window.inner_size().width, window.inner_size().height],
                 pixels_per_point: window.scale_factor() as f32,
@@ -208,8 +624,563 @@ impl GoLManager {
                 let fps_text = ms_per_frame_opt.map_or("Fps: NaN".to_string(), |ms_per_frame| {
                     format!("Fps: {:.1}", 1000.0 / ms_per_frame)
                 });
-                add_gui(ui, &fps_text, &mut self.config);
+                gui_actions = add_gui(ui, &fps_text, &mut self.config, &mut self.gui_state, &self.library, &mut self.drawing);
             },
         );
+
+        if let Some(texture) = screenshot_texture {
+            self.pending_window_screenshot = Some((PathBuf::from(&self.gui_state.screenshot.path), texture));
+        }
+
+        gui_actions.extend(self.keyboard_controller.take_actions());
+        for action in gui_actions {
+            self.handle_gui_action(action, device, queue);
+        }
+    }
+
+    /// One view is drawn with the frame encoder. Views share the renderer uniforms, so with more of them
+    /// each one is submitted right away with its own encoder. Minimap goes over the last one
+    fn render_viewports(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target_view: &wgpu::TextureView,
+        window: &winit::window::Window,
+    ) {
+        let minimap_view = self.get_minimap_view(window);
+        if let [viewport] = self.viewports.get_all() {
+            self.renderer.set_minimap(minimap_view);
+            self.renderer.rerender(
+                device,
+                queue,
+                encoder,
+                &self.gol,
+                target_view,
+                viewport.camera.get_matrix(),
+                self.render_quad_transform,
+            );
+            return;
+        }
+        let count = self.viewports.get_all().len();
+        let mut minimap_view = Some(minimap_view);
+        for (index, viewport) in self.viewports.get_all().iter().enumerate() {
+            let is_last = index + 1 == count;
+            self.renderer.set_minimap(if is_last { minimap_view.take().flatten() } else { None });
+            let mut viewport_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Viewport Encoder"),
+            });
+            self.renderer.rerender_viewport(
+                device,
+                queue,
+                &mut viewport_encoder,
+                &self.gol,
+                target_view,
+                viewport.camera.get_matrix(),
+                self.render_quad_transform,
+                viewport.rect,
+            );
+            queue.submit(Some(viewport_encoder.finish()));
+        }
+    }
+
+    fn handle_gui_action(&mut self, action: GuiAction, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let status = match action {
+            GuiAction::ImportPattern => self
+                .import_pattern(device, queue)
+                .unwrap_or_else(|err| format!("Import failed: {}", err)),
+            GuiAction::ExportPattern => self
+                .export_pattern(device, queue)
+                .unwrap_or_else(|err| format!("Export failed: {}", err)),
+            GuiAction::PlaceMacrocellWindow => {
+                let (Some(macrocell), Some(window)) = (&self.macrocell, &self.gui_state.macrocell_window) else {
+                    return;
+                };
+                // grid could have been resized by loading a session since the import
+                let (gol_width, gol_height) = self.gol.get_size();
+                let pattern = macrocell.window(window.x, window.y, window.width.min(gol_width), window.height.min(gol_height));
+                self.place_pattern_at_screen_center(&pattern, device, queue);
+                format!("Placed {}x{} window", pattern.width, pattern.height)
+            }
+            GuiAction::StartRecording => match self.start_recording(device, queue) {
+                Ok(()) => return,
+                Err(err) => format!("Recording failed: {}", err),
+            },
+            GuiAction::StopRecording => {
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.stop(device);
+                }
+                "Saving recording...".to_string()
+            }
+            GuiAction::SaveSession => match self.save_session(device, queue, &PathBuf::from(&self.gui_state.session_path)) {
+                Ok(()) => format!("Session saved to {}", self.gui_state.session_path),
+                Err(err) => format!("Saving session failed: {}", err),
+            },
+            GuiAction::LoadSession => match self.load_session(device, queue, &PathBuf::from(&self.gui_state.session_path)) {
+                Ok(()) => format!("Session loaded from {}", self.gui_state.session_path),
+                Err(err) => format!("Loading session failed: {}", err),
+            },
+            GuiAction::RecordVisibleArea => match self.get_visible_area() {
+                Some((x, y, width, height)) => {
+                    self.gui_state.recording.region = [x, y, width, height];
+                    return;
+                }
+                None => "Nothing visible to record".to_string(),
+            },
+            GuiAction::ArmLibraryPattern(index) => {
+                let entry = &self.library.entries()[index];
+                self.drawing.arm(entry.pattern.clone());
+                self.gui_state.library.armed_entry = Some(index);
+                format!("Click to place {}", entry.name)
+            }
+            GuiAction::AddLibraryDirectory => {
+                let path = PathBuf::from(self.gui_state.library.new_directory.trim());
+                let status = match self.library.add_directory(path) {
+                    Ok(count) => format!("Loaded {} patterns from {}", count, self.gui_state.library.new_directory),
+                    Err(err) => format!("Adding directory failed: {}", err),
+                };
+                self.on_library_rescanned();
+                status
+            }
+            GuiAction::RemoveLibraryDirectory(path) => {
+                self.library.remove_directory(&path);
+                self.on_library_rescanned();
+                return;
+            }
+            GuiAction::EditSelection(operation) => self.edit_selection(operation, device, queue),
+            GuiAction::SelectAll => {
+                let (width, height) = self.gol.get_size();
+                self.drawing.select_area(0, 0, width, height);
+                return;
+            }
+            GuiAction::Paste => match self.clipboard.paste(self.gol.get_size()) {
+                Some(pattern) => {
+                    let status = format!("Click to paste {}x{} pattern", pattern.width, pattern.height);
+                    self.drawing.arm(pattern);
+                    self.gui_state.library.armed_entry = None;
+                    status
+                }
+                None => "Clipboard is empty".to_string(),
+            },
+            GuiAction::Undo => {
+                let generation = self.gol.get_generation();
+                let status = self.history.undo(&mut self.gol, device, queue);
+                // tracked observations are from the generations that were undone
+                if self.gol.get_generation() < generation {
+                    self.stop_tracking();
+                }
+                status.unwrap_or_else(|| "Nothing to undo".to_string())
+            }
+            GuiAction::Redo => self
+                .history
+                .redo(&self.gol, device, queue)
+                .unwrap_or_else(|| "Nothing to redo".to_string()),
+            GuiAction::ArmText => {
+                match render_text(&self.gui_state.text, &self.gui_state.text_options) {
+                    Ok(pattern) if pattern.width == 0 => "Type some text first".to_string(),
+                    Ok(pattern) => {
+                        let status = format!("Click to place {}x{} text", pattern.width, pattern.height);
+                        self.drawing.arm(pattern);
+                        self.gui_state.library.armed_entry = None;
+                        status
+                    }
+                    Err(err) => format!("Can't place text: {}", err),
+                }
+            }
+            GuiAction::SetRotation(rotation) => {
+                self.viewports.get_focused_mut().camera.rotation = wrap_angle(rotation);
+                return;
+            }
+            GuiAction::FitToContent => {
+                let (width, height) = self.gol.get_size();
+                let cells = pollster::block_on(self.gol.read_area(device, queue, 0, 0, width, height));
+                match Pattern::new(cells, width, height).content_bounds() {
+                    Some(bounds) => {
+                        self.fit_view(bounds);
+                        return;
+                    }
+                    None => "Grid is empty".to_string(),
+                }
+            }
+            GuiAction::Step => {
+                if self.config.is_paused {
+                    self.advance_generation(device, queue);
+                }
+                return;
+            }
+            GuiAction::Screenshot(kind) => {
+                if self.screenshot_thread.is_some() || self.pending_window_screenshot.is_some() {
+                    "Previous screenshot is still being saved".to_string()
+                } else {
+                    self.screenshot_request = Some(kind);
+                    return;
+                }
+            }
+            GuiAction::ResetShaders => {
+                let shaders = &mut self.gui_state.shaders;
+                shaders.is_watching = false;
+                for kind in ShaderKind::ALL {
+                    shaders.set_error(kind, None);
+                }
+                self.shader_watcher = None;
+                self.simulation_shader = None;
+                let result = self
+                    .gol
+                    .set_shader(device, gol::SHADER_SOURCE)
+                    .and_then(|()| self.renderer.set_shader(device, gol_renderer::SHADER_SOURCE));
+                match result {
+                    Ok(()) => "Built-in shaders restored".to_string(),
+                    Err(err) => format!("Can't restore built-in shaders: {}", err),
+                }
+            }
+            GuiAction::ToggleGrid => {
+                let grid = &mut self.gui_state.view.grid;
+                grid.is_visible = !grid.is_visible;
+                return;
+            }
+            GuiAction::ToggleTracking => {
+                if self.tracker.take().is_some() {
+                    self.stop_tracking();
+                    "Tracking stopped".to_string()
+                } else {
+                    match self.drawing.get_selection(&self.gol) {
+                        Some(selection) => match Tracker::start(&self.gol, device, queue, selection) {
+                            Ok(tracker) => {
+                                self.tracker = Some(tracker);
+                                self.tracked_viewport = self.viewports.get_focused_index();
+                                "Tracking the selected object".to_string()
+                            }
+                            Err(err) => format!("Can't track: {}", err),
+                        },
+                        None => "Select the object first".to_string(),
+                    }
+                }
+            }
+            GuiAction::FitToSelection => match self.drawing.get_selection(&self.gol) {
+                Some(selection) => {
+                    self.fit_view(selection);
+                    return;
+                }
+                None => "Nothing is selected".to_string(),
+            },
+            GuiAction::SaveBookmark(index) => {
+                let bookmarks = &mut self.gui_state.view.bookmarks;
+                let view = self.viewports.get_focused().camera.get_view();
+                let index = match index.filter(|index| *index < bookmarks.len()) {
+                    Some(index) => {
+                        bookmarks[index].view = view;
+                        index
+                    }
+                    None => {
+                        let name = std::mem::take(&mut self.gui_state.view.new_bookmark_name);
+                        let name = if name.trim().is_empty() { format!("View {}", bookmarks.len() + 1) } else { name };
+                        bookmarks.push(ViewBookmark { name, view });
+                        bookmarks.len() - 1
+                    }
+                };
+                format!("Saved bookmark {} ({})", index + 1, bookmarks[index].name)
+            }
+            GuiAction::GoToBookmark(index) => match self.gui_state.view.bookmarks.get(index) {
+                Some(bookmark) => {
+                    let viewport = self.viewports.get_focused_mut();
+                    viewport.camera_controller.animate_to(&viewport.camera, bookmark.view);
+                    return;
+                }
+                None => format!("No bookmark {}, Ctrl+{} saves it", index + 1, index + 1),
+            },
+            GuiAction::DeleteBookmark(index) => {
+                self.gui_state.view.bookmarks.remove(index);
+                return;
+            }
+            GuiAction::LoadThemes => match load_all_themes(Path::new(&self.gui_state.theme.path)) {
+                Ok(themes) => {
+                    let theme_gui = &mut self.gui_state.theme;
+                    let selected_name = &theme_gui.get_theme().name;
+                    theme_gui.selected = themes.iter().position(|theme| &theme.name == selected_name).unwrap_or(0);
+                    theme_gui.themes = themes;
+                    format!("Loaded {} themes", theme_gui.themes.len())
+                }
+                Err(err) => format!("Can't load themes: {}", err),
+            },
+            GuiAction::SaveThemes => {
+                let theme_gui = &self.gui_state.theme;
+                match save_themes(Path::new(&theme_gui.path), &theme_gui.themes) {
+                    Ok(()) => format!("Saved {} themes to {}", theme_gui.themes.len(), theme_gui.path),
+                    Err(err) => format!("Can't save themes: {}", err),
+                }
+            }
+            GuiAction::RescanLibrary => {
+                self.library.rescan();
+                self.on_library_rescanned();
+                format!("Library has {} patterns", self.library.entries().len())
+            }
+        };
+        self.gui_state.status = Some(status);
+    }
+
+    fn edit_selection(&mut self, operation: SelectionOperation, device: &wgpu::Device, queue: &wgpu::Queue) -> String {
+        let Some((x, y, width, height)) = self.drawing.get_selection(&self.gol) else {
+            return "Nothing is selected".to_string();
+        };
+        let fill_density = self.gui_state.fill_density;
+        let edit = |cells: &mut [u8]| match operation {
+            SelectionOperation::Copy => {}
+            SelectionOperation::Cut | SelectionOperation::Clear => cells.fill(0),
+            SelectionOperation::Invert => cells.iter_mut().for_each(|cell| *cell = (*cell == 0) as u8),
+            SelectionOperation::RandomFill => {
+                let mut rng = rand::thread_rng();
+                cells.iter_mut().for_each(|cell| *cell = (rng.gen::<f32>() < fill_density) as u8);
+            }
+        };
+        match operation {
+            SelectionOperation::Copy | SelectionOperation::Cut => {
+                let cells = pollster::block_on(self.gol.read_area(device, queue, x, y, width, height));
+                let copy_result = self.clipboard.copy(Pattern::new(cells, width, height));
+                if operation == SelectionOperation::Cut {
+                    self.history.edit_area(&self.gol, device, queue, x, y, width, height, edit);
+                }
+                let verb = if operation == SelectionOperation::Cut { "Cut" } else { "Copied" };
+                return match copy_result {
+                    Ok(()) => format!("{} {}x{} area", verb, width, height),
+                    Err(err) => format!("{} {}x{} area, but not to the system clipboard: {}", verb, width, height, err),
+                };
+            }
+            SelectionOperation::Clear | SelectionOperation::Invert | SelectionOperation::RandomFill => {
+                self.history.edit_area(&self.gol, device, queue, x, y, width, height, edit);
+            }
+        }
+        format!("Changed {}x{} area", width, height)
+    }
+
+    /// Animates the camera to show the cells (x, y, width, height)
+    fn fit_view(&mut self, (x, y, width, height): (u32, u32, u32, u32)) {
+        let corner_a = self.cell_to_world(vec2(x as f32, y as f32));
+        let corner_b = self.cell_to_world(vec2((x + width) as f32, (y + height) as f32));
+        let viewport = self.viewports.get_focused_mut();
+        let view = CameraController::get_view_fitting(&viewport.camera, corner_a.min(corner_b), corner_a.max(corner_b));
+        viewport.camera_controller.animate_to(&viewport.camera, view);
+    }
+
+    /// Outlines the focused view
+    fn get_minimap_view(&self, window: &winit::window::Window) -> Option<MinimapView> {
+        let screen_size = (window.inner_size().width, window.inner_size().height);
+        let rect = self.gui_state.view.minimap.get_rect(screen_size, self.gol.get_size())?;
+        let (gol_width, gol_height) = self.gol.get_size();
+        let grid_size = vec2(gol_width as f32, gol_height as f32);
+        let viewport = [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)].map(|ndc| {
+            GoLRenderer::ndc_to_gol_uv(ndc, self.viewports.get_focused().camera.get_matrix(), self.render_quad_transform) * grid_size
+        });
+        Some(MinimapView { rect, viewport })
+    }
+
+    /// Same mapping as the grid shader: uv (0, 0) is the top left corner of the quad at (-1, 1)
+    fn cell_to_world(&self, cell: Vec2) -> Vec2 {
+        let (gol_width, gol_height) = self.gol.get_size();
+        let uv = cell / vec2(gol_width as f32, gol_height as f32);
+        self.render_quad_transform.transform_point2(vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0))
+    }
+
+    fn stop_tracking(&mut self) {
+        self.tracker = None;
+        self.gui_state.view.tracking = None;
+        for viewport in self.viewports.get_all_mut() {
+            viewport.camera_controller.set_follow_target(None);
+        }
+    }
+
+    /// Entry indices are not stable between scans
+    fn on_library_rescanned(&mut self) {
+        self.gui_state.library.thumbnails.clear();
+        self.gui_state.library.armed_entry = None;
+    }
+
+    pub fn save_session(&self, device: &wgpu::Device, queue: &wgpu::Queue, path: &Path) -> Result<(), SessionError> {
+        let (grid_width, grid_height) = self.gol.get_size();
+        let session = Session {
+            grid_width,
+            grid_height,
+            cells: pollster::block_on(self.gol.read_area(device, queue, 0, 0, grid_width, grid_height)),
+            generation: self.gol.get_generation(),
+            rule: CONWAY_RULE.to_string(),
+            is_paused: self.config.is_paused,
+            target_tps: self.config.target_tps,
+            camera_position: self.viewports.get_main().camera.position,
+            camera_zoom: self.viewports.get_main().camera.zoom,
+            camera_rotation: self.viewports.get_main().camera.rotation,
+        };
+        session.save(path)
+    }
+
+    /// Grid is recreated if session has a different size. Running recording is stopped and saved, its region belongs to the old grid
+    pub fn load_session(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, path: &Path) -> Result<(), SessionError> {
+        let session = Session::load(path, device.limits().max_texture_dimension_2d)?;
+        if session.rule != CONWAY_RULE {
+            return Err(SessionError::Corrupted(format!("rule {} is not supported", session.rule)));
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.stop(device);
+        }
+        if self.gol.get_size() != (session.grid_width, session.grid_height) {
+            self.gol = GoL::new(device, session.grid_width, session.grid_height);
+            if let Some(source) = &self.simulation_shader {
+                // it compiled before, the layout is the same for every grid
+                self.gol.set_shader(device, source).ok();
+            }
+            self.render_quad_transform = Self::get_render_quad_transform(&self.gol);
+            self.drawing.symmetry.center = vec2(session.grid_width as f32, session.grid_height as f32) / 2.0;
+        }
+        // edits of the previous grid can't be applied to the loaded one
+        self.history.clear();
+        self.stop_tracking();
+        self.gol.write_area(queue, &session.cells, 0, 0, session.grid_width, session.grid_height);
+        self.gol.set_generation(session.generation);
+        self.config.is_paused = session.is_paused;
+        self.config.target_tps = session.target_tps;
+        let camera = &mut self.viewports.get_main_mut().camera;
+        camera.position = session.camera_position;
+        camera.zoom = session.camera_zoom;
+        camera.rotation = session.camera_rotation;
+        self.time_accumulator = Duration::from_secs(0);
+        Ok(())
+    }
+
+    fn start_recording(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<(), String> {
+        if self.recorder.is_some() {
+            return Err("previous recording is still being saved".to_string());
+        }
+        let recording = &self.gui_state.recording;
+        let [x, y, width, height] = recording.region;
+        let settings = RecordingSettings {
+            path: PathBuf::from(&recording.path),
+            format: recording.format,
+            region: (x, y, width, height),
+            every_generations: recording.every_generations,
+            scale: recording.scale,
+            frame_delay_ms: recording.frame_delay_ms,
+            colors: recording.palette.colors(self.gui_state.theme.get_theme()),
+            drop_frames_when_busy: true,
+        };
+        settings.check_region(self.gol.get_size())?;
+        let mut recorder = Recorder::start(settings);
+        recorder.on_generation(&self.gol, device, queue);
+        self.gui_state.recording.progress = Some(recorder.get_status());
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Macrocell files are kept sparse, since they can be much bigger than the grid. Only the window around pattern center is placed right away
+    fn import_pattern(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<String, PatternError> {
+        let path = Path::new(&self.gui_state.pattern_path);
+        if pattern_io::is_png(path) {
+            let pattern = pattern_io::load_png(path, &self.gui_state.image_options)?;
+            self.place_pattern_at_screen_center(&pattern, device, queue);
+            return Ok(format!("Imported {}x{} image", pattern.width, pattern.height));
+        }
+        let (format, text) = pattern_io::read_pattern_file(path)?;
+        if format == PatternFormat::Macrocell {
+            let macrocell = Macrocell::parse(&text)?;
+            let (gol_width, gol_height) = self.gol.get_size();
+            let content_size = macrocell.content_size();
+            let width = content_size.0.min(gol_width as u64) as u32;
+            let height = content_size.1.min(gol_height as u64) as u32;
+            let window = MacrocellWindow {
+                content_size,
+                alive_cells: macrocell.alive_cell_count(),
+                x: (content_size.0 - width as u64) / 2,
+                y: (content_size.1 - height as u64) / 2,
+                width: width.max(1),
+                height: height.max(1),
+                max_size: (gol_width, gol_height),
+            };
+            let pattern = macrocell.window(window.x, window.y, window.width, window.height);
+            self.place_pattern_at_screen_center(&pattern, device, queue);
+            self.gui_state.macrocell_window = Some(window);
+            self.macrocell = Some(macrocell);
+            return Ok(format!(
+                "Imported {}x{} macrocell pattern, placed {}x{} window",
+                content_size.0, content_size.1, pattern.width, pattern.height
+            ));
+        }
+        let pattern = pattern_io::parse_pattern(&text, format, self.gol.get_size())?;
+        self.place_pattern_at_screen_center(&pattern, device, queue);
+        Ok(format!("Imported {}x{} pattern", pattern.width, pattern.height))
+    }
+
+    fn export_pattern(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<String, PatternError> {
+        let area = match self.gui_state.export_region {
+            ExportRegion::AliveCells => None,
+            ExportRegion::VisibleArea => match self.get_visible_area() {
+                Some(area) => Some(area),
+                None => return Ok("Nothing visible to export".to_string()),
+            },
+            ExportRegion::Selection => match self.drawing.get_selection(&self.gol) {
+                Some(area) => Some(area),
+                None => return Ok("Nothing selected to export".to_string()),
+            },
+        };
+        let pattern = match area {
+            Some((x, y, width, height)) => {
+                let data = pollster::block_on(self.gol.read_area(device, queue, x, y, width, height));
+                Pattern::new(data, width, height)
+            }
+            None => self.read_alive_cells(device, queue),
+        };
+        // empty pattern can't be imported back by most formats
+        if pattern.content_bounds().is_none() {
+            return Ok("Nothing to export, there are no alive cells".to_string());
+        }
+        let path = Path::new(&self.gui_state.pattern_path);
+        match self.gui_state.export_format {
+            ExportFormat::Pattern(format) => pattern_io::save_pattern(path, &pattern, format)?,
+            ExportFormat::Png => {
+                let colors = self.gui_state.image_palette.colors(self.gui_state.theme.get_theme());
+                pattern_io::save_png(path, &pattern, &colors)?
+            }
+        }
+        Ok(format!("Exported {}x{} pattern", pattern.width, pattern.height))
+    }
+
+    /// Cell which is currently at the center of the focused view. Can be outside of the grid
+    fn get_screen_center_cell(&self) -> (i64, i64) {
+        let view_proj = self.viewports.get_focused().camera.get_matrix();
+        let uv = GoLRenderer::ndc_to_gol_uv(Vec2::ZERO, view_proj, self.render_quad_transform);
+        let (width, height) = self.gol.get_size();
+        let cell = uv * vec2(width as f32, height as f32);
+        (cell.x.floor() as i64, cell.y.floor() as i64)
+    }
+
+    /// Part of the grid covered by the focused view as (x, y, width, height), clamped to the grid.
+    /// None when the view is completely outside of the grid
+    fn get_visible_area(&self) -> Option<(u32, u32, u32, u32)> {
+        let (width, height) = self.gol.get_size();
+        let size = vec2(width as f32, height as f32);
+        let view_proj = self.viewports.get_focused().camera.get_matrix();
+        let corners = [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, 1.0)]
+            .map(|ndc| GoLRenderer::ndc_to_gol_uv(ndc, view_proj, self.render_quad_transform) * size);
+        let min = corners.iter().fold(Vec2::INFINITY, |acc, c| acc.min(*c)).floor().max(-size);
+        let max = corners.iter().fold(Vec2::NEG_INFINITY, |acc, c| acc.max(*c)).ceil().min(size * 2.0);
+        let area_size = (max - min).max(Vec2::ONE);
+        self.gol.clip_area(min.x as i64, min.y as i64, area_size.x as u32, area_size.y as u32)
+    }
+
+    fn place_pattern_at_screen_center(&mut self, pattern: &Pattern, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let (center_x, center_y) = self.get_screen_center_cell();
+        self.history.write_area_clipped(
+            &self.gol,
+            device,
+            queue,
+            &pattern.data,
+            center_x - pattern.width as i64 / 2,
+            center_y - pattern.height as i64 / 2,
+            pattern.width,
+            pattern.height,
+        );
+    }
+
+    /// Reads the whole grid and crops it to the bounding box of alive cells
+    fn read_alive_cells(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Pattern {
+        let (width, height) = self.gol.get_size();
+        let data = pollster::block_on(self.gol.read_area(device, queue, 0, 0, width, height));
+        Pattern::new(data, width, height).cropped_to_content()
     }
 }
\ No newline at end of file
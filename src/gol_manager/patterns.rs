@@ -2,10 +2,83 @@
 
 use std::sync::OnceLock;
 
+#[derive(Clone)]
 pub struct Pattern {
     pub data: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    pub name: Option<String>,
+    /// Free-form comment lines from the pattern file, without format-specific prefixes like "!" or "#D"
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    pub fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self { data, width, height, name: None, comments: Vec::new() }
+    }
+
+    /// Builds the pattern from a list of alive cell coordinates. Coordinates can be negative, pattern origin is moved to the top left alive cell.
+    pub fn from_alive_cells(cells: &[(i64, i64)]) -> Self {
+        if cells.is_empty() {
+            return Self::new(Vec::new(), 0, 0);
+        }
+        let min_x = cells.iter().map(|c| c.0).min().unwrap();
+        let min_y = cells.iter().map(|c| c.1).min().unwrap();
+        let max_x = cells.iter().map(|c| c.0).max().unwrap();
+        let max_y = cells.iter().map(|c| c.1).max().unwrap();
+        let width = (max_x - min_x + 1) as u32;
+        let height = (max_y - min_y + 1) as u32;
+        let mut data = vec![0; (width * height) as usize];
+        for (x, y) in cells {
+            data[((y - min_y) as u32 * width + (x - min_x) as u32) as usize] = 1;
+        }
+        Self::new(data, width, height)
+    }
+
+    pub fn alive_cells(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.width;
+        self.data.iter().enumerate().filter(|(_, v)| **v != 0).map(move |(i, _)| (i as u32 % width, i as u32 / width))
+    }
+
+    /// Cuts away dead rows and columns around alive cells. Pattern with no alive cells becomes 0x0.
+    /// Bounding box of alive cells as (x, y, width, height), None when all cells are dead
+    pub fn content_bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y) in self.alive_cells() {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        (min_x <= max_x).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    pub fn cropped_to_content(&self) -> Self {
+        let cells: Vec<(i64, i64)> = self.alive_cells().map(|(x, y)| (x as i64, y as i64)).collect();
+        let mut cropped = Self::from_alive_cells(&cells);
+        cropped.name = self.name.clone();
+        cropped.comments = self.comments.clone();
+        cropped
+    }
+
+    pub fn rotated_clockwise(&self) -> Self {
+        let (width, height) = (self.height, self.width);
+        let data = (0..width * height).map(|i| {
+            let (x, y) = (i % width, i / width);
+            self.data[((self.height - 1 - x) * self.width + y) as usize]
+        }).collect();
+        Self { data, width, height, name: self.name.clone(), comments: self.comments.clone() }
+    }
+
+    pub fn flipped_horizontally(&self) -> Self {
+        let data = self.data.chunks(self.width.max(1) as usize).flat_map(|row| row.iter().rev().copied()).collect();
+        Self { data, ..self.clone() }
+    }
+
+    pub fn flipped_vertically(&self) -> Self {
+        let data = self.data.chunks(self.width.max(1) as usize).rev().flatten().copied().collect();
+        Self { data, ..self.clone() }
+    }
 }
 
 pub fn get_blinker() -> &'static Pattern {
@@ -14,6 +87,8 @@ pub fn get_blinker() -> &'static Pattern {
         data: vec![0, 1, 0, 0, 1, 0, 0, 1, 0],
         width: 3,
         height: 3,
+        name: Some("Blinker".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -28,6 +103,8 @@ pub fn get_loaf() -> &'static Pattern {
         ],
         width: 4,
         height: 4,
+        name: Some("Loaf".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -42,6 +119,8 @@ pub fn get_toad() -> &'static Pattern {
         ],
         width: 4,
         height: 4,
+        name: Some("Toad".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -56,6 +135,8 @@ pub fn get_light_weight_spaceship() -> &'static Pattern {
         ],
         width: 5,
         height: 4,
+        name: Some("Lightweight spaceship".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -71,6 +152,8 @@ pub fn get_middle_weight_spaceship() -> &'static Pattern {
         ],
         width: 6,
         height: 5,
+        name: Some("Middleweight spaceship".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -86,6 +169,8 @@ pub fn get_heavy_weight_spaceship() -> &'static Pattern {
         ],
         width: 7,
         height: 5,
+        name: Some("Heavyweight spaceship".to_string()),
+        comments: Vec::new(),
     })
 }
 
@@ -106,5 +191,7 @@ pub fn get_penta_decathlon() -> &'static Pattern {
         ],
         width: 9,
         height: 10,
+        name: Some("Penta-decathlon".to_string()),
+        comments: Vec::new(),
     })
 }
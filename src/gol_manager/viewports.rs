@@ -0,0 +1,250 @@
+use super::camera::{Camera, CameraController};
+use glam::{vec2, Vec2};
+use std::collections::HashSet;
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+/// Main view included
+pub const MAX_VIEWPORTS: usize = 4;
+/// Smallest side of a picture in picture view, as a fraction of the window
+pub const MIN_INSET_SIZE: f32 = 0.1;
+const CAMERA_SPEED: f32 = 3.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ViewportLayout {
+    Single,
+    /// Two views next to each other
+    SideBySide,
+    /// Two views above each other
+    Stacked,
+    /// Main view over the whole window and smaller views over it
+    PictureInPicture,
+}
+
+impl ViewportLayout {
+    pub const ALL: [ViewportLayout; 4] = [
+        ViewportLayout::Single,
+        ViewportLayout::SideBySide,
+        ViewportLayout::Stacked,
+        ViewportLayout::PictureInPicture,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewportLayout::Single => "Single",
+            ViewportLayout::SideBySide => "Side by side",
+            ViewportLayout::Stacked => "Top and bottom",
+            ViewportLayout::PictureInPicture => "Picture in picture",
+        }
+    }
+}
+
+pub struct ViewportSettings {
+    pub layout: ViewportLayout,
+    /// Picture in picture views after the main one, (x, y, width, height) as fractions of the window
+    pub insets: Vec<[f32; 4]>,
+}
+
+impl ViewportSettings {
+    pub fn new() -> Self {
+        Self {
+            layout: ViewportLayout::Single,
+            insets: vec![Self::get_default_inset(0)],
+        }
+    }
+
+    /// Top right corner, every next one is shifted so they don't cover each other completely
+    pub fn get_default_inset(index: usize) -> [f32; 4] {
+        let offset = index as f32 * 0.05;
+        [0.63 - offset, 0.03 + offset, 0.34, 0.34]
+    }
+
+    /// Keeps the inset in the window and not smaller than MIN_INSET_SIZE
+    pub fn clamp_inset([x, y, width, height]: [f32; 4]) -> [f32; 4] {
+        let width = width.clamp(MIN_INSET_SIZE, 1.0);
+        let height = height.clamp(MIN_INSET_SIZE, 1.0);
+        [x.clamp(0.0, 1.0 - width), y.clamp(0.0, 1.0 - height), width, height]
+    }
+
+    /// Views shown with the layout, main one included
+    pub fn get_count(&self) -> usize {
+        match self.layout {
+            ViewportLayout::Single => 1,
+            ViewportLayout::SideBySide | ViewportLayout::Stacked => 2,
+            ViewportLayout::PictureInPicture => 1 + self.insets.len().min(MAX_VIEWPORTS - 1),
+        }
+    }
+
+    /// (x, y, width, height) of every view in physical pixels, later ones are drawn over earlier ones.
+    /// Whole pixels, so integer zoom lines cells up with pixels in every view
+    pub fn get_rects(&self, window_size: PhysicalSize<u32>) -> Vec<[u32; 4]> {
+        let (width, height) = (window_size.width.max(2), window_size.height.max(2));
+        match self.layout {
+            ViewportLayout::Single => vec![[0, 0, width, height]],
+            ViewportLayout::SideBySide => vec![[0, 0, width / 2, height], [width / 2, 0, width - width / 2, height]],
+            ViewportLayout::Stacked => vec![[0, 0, width, height / 2], [0, height / 2, width, height - height / 2]],
+            ViewportLayout::PictureInPicture => {
+                let window = vec2(width as f32, height as f32);
+                let insets = self.insets.iter().take(MAX_VIEWPORTS - 1).map(|inset| {
+                    let [x, y, inset_width, inset_height] = Self::clamp_inset(*inset);
+                    let corner = (vec2(x, y) * window).round().min(window - Vec2::ONE);
+                    // rounded separately, so without the clamp an inset against the edge can end a pixel past it
+                    let size = (vec2(inset_width, inset_height) * window).round().min(window - corner).max(Vec2::ONE);
+                    [corner.x as u32, corner.y as u32, size.x as u32, size.y as u32]
+                });
+                std::iter::once([0, 0, width, height]).chain(insets).collect()
+            }
+        }
+    }
+}
+
+/// Part of the window showing the grid through its own camera
+pub struct Viewport {
+    pub camera: Camera,
+    pub camera_controller: CameraController,
+    /// x, y, width, height in physical pixels, see ViewportSettings::get_rects()
+    pub rect: [u32; 4],
+}
+
+impl Viewport {
+    fn new(camera: &Camera) -> Self {
+        let mut new_camera = Camera::new(camera.aspect_ratio);
+        new_camera.set_view(camera.get_view());
+        Self {
+            camera: new_camera,
+            camera_controller: CameraController::new(CAMERA_SPEED),
+            rect: [0, 0, 1, 1],
+        }
+    }
+
+    pub fn get_size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.rect[2], self.rect[3])
+    }
+
+    /// Window point in physical pixels
+    pub fn contains(&self, point: Vec2) -> bool {
+        let [x, y, width, height] = self.rect.map(|value| value as f32);
+        point.x >= x && point.y >= y && point.x < x + width && point.y < y + height
+    }
+}
+
+/// All views of the grid, the first one is the main one. Input goes to the view under the cursor,
+/// GUI and shortcut camera actions to the focused one, the one that got a click, key or wheel last
+pub struct Viewports {
+    viewports: Vec<Viewport>,
+    hovered: usize,
+    focused: usize,
+    /// While any mouse button is held, the hovered view keeps getting input until all are released, e.g. when dragging over an edge
+    held_buttons: HashSet<MouseButton>,
+}
+
+impl Viewports {
+    pub fn new(aspect_ratio: f32) -> Self {
+        Self {
+            viewports: vec![Viewport::new(&Camera::new(aspect_ratio))],
+            hovered: 0,
+            focused: 0,
+            held_buttons: HashSet::new(),
+        }
+    }
+
+    /// Adds or removes views to match the settings and lays them out, new views start where the main one is
+    pub fn update_layout(&mut self, settings: &ViewportSettings, window_size: PhysicalSize<u32>) {
+        let rects = settings.get_rects(window_size);
+        self.viewports.truncate(rects.len());
+        while self.viewports.len() < rects.len() {
+            let viewport = Viewport::new(&self.viewports[0].camera);
+            self.viewports.push(viewport);
+        }
+        for (viewport, rect) in self.viewports.iter_mut().zip(rects) {
+            viewport.rect = rect;
+            viewport.camera.aspect_ratio = rect[2] as f32 / rect[3] as f32;
+        }
+        if self.hovered >= self.viewports.len() {
+            self.hovered = 0;
+            self.held_buttons.clear();
+        }
+        if self.focused >= self.viewports.len() {
+            self.focused = 0;
+        }
+    }
+
+    /// Updates the hovered and focused view and returns the event with the cursor position relative to the hovered one
+    pub fn route_input(&mut self, event: &WindowEvent) -> WindowEvent {
+        let mut event = event.clone();
+        match &mut event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let point = vec2(position.x as f32, position.y as f32);
+                if self.held_buttons.is_empty() {
+                    // topmost view under the cursor
+                    let hovered = self.viewports.iter().rposition(|viewport| viewport.contains(point)).unwrap_or(0);
+                    if hovered != self.hovered {
+                        self.viewports[self.hovered].camera_controller.release_input();
+                        self.hovered = hovered;
+                    }
+                }
+                let [x, y, _, _] = self.viewports[self.hovered].rect;
+                position.x -= x as f64;
+                position.y -= y as f64;
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if *state == ElementState::Pressed {
+                    self.held_buttons.insert(*button);
+                    self.focused = self.hovered;
+                } else {
+                    self.held_buttons.remove(button);
+                }
+            }
+            // releases outside of the window are not reported
+            WindowEvent::Focused(false) => self.held_buttons.clear(),
+            WindowEvent::MouseWheel { .. } => self.focused = self.hovered,
+            WindowEvent::KeyboardInput { event: key_event, .. } if key_event.state == ElementState::Pressed => {
+                self.focused = self.hovered;
+            }
+            _ => {}
+        }
+        event
+    }
+
+    pub fn get_hovered(&self) -> &Viewport {
+        &self.viewports[self.hovered]
+    }
+
+    pub fn get_hovered_mut(&mut self) -> &mut Viewport {
+        &mut self.viewports[self.hovered]
+    }
+
+    pub fn get_focused_index(&self) -> usize {
+        self.focused
+    }
+
+    pub fn get_focused(&self) -> &Viewport {
+        &self.viewports[self.focused]
+    }
+
+    pub fn get_focused_mut(&mut self) -> &mut Viewport {
+        &mut self.viewports[self.focused]
+    }
+
+    /// Saved with the session
+    pub fn get_main(&self) -> &Viewport {
+        &self.viewports[0]
+    }
+
+    pub fn get_main_mut(&mut self) -> &mut Viewport {
+        &mut self.viewports[0]
+    }
+
+    /// None when the view was removed with a layout change
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Viewport> {
+        self.viewports.get_mut(index)
+    }
+
+    pub fn get_all(&self) -> &[Viewport] {
+        &self.viewports
+    }
+
+    pub fn get_all_mut(&mut self) -> &mut [Viewport] {
+        &mut self.viewports
+    }
+}
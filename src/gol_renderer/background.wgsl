@@ -0,0 +1,26 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const full_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+// covers the whole viewport, a clear only for part of the target
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(full_quad[vertex_index], 0.0, 1.0);
+    return out;
+}
+
+// the colour comes from the blend constant, see GoLRenderer::rerender_viewport()
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
@@ -0,0 +1,225 @@
+use super::GoLRenderer;
+use crate::gol::GoL;
+use egui_wgpu::wgpu;
+
+/// Half precision is enough, level 1 is already in quarters
+const DENSITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Mip chain with the fraction of alive cells (r), cells born in the last generation (g) and cells that died in it (b).
+/// Level 0 has one texel per 2x2 cells (a texel per cell would take 8 bytes per cell) and every next level averages 2x2 texels
+/// of the previous one. Sampling it trilinearly at log2(cells per pixel) - 1 gives the density of the cells a pixel covers,
+/// so zoomed out views don't skip cells and don't shimmer
+pub struct DensityPyramid {
+    base_bind_group_layout: wgpu::BindGroupLayout,
+    base_pipeline: wgpu::RenderPipeline,
+    downsample_bind_group_layout: wgpu::BindGroupLayout,
+    downsample_pipeline: wgpu::RenderPipeline,
+    /// Texture and sampler for shaders that read the pyramid, see get_bind_group()
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Recreated when the grid size changes
+    texture: Option<wgpu::Texture>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl DensityPyramid {
+    pub fn new(device: &wgpu::Device) -> Self {
+        // current and previous cells
+        let base_bind_group_layout = Self::create_source_bind_group_layout(device, wgpu::TextureSampleType::Uint, 2);
+        let base_pipeline = GoLRenderer::create_pipeline(
+            device,
+            DENSITY_FORMAT,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&base_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("density_base.wgsl")),
+            wgpu::BlendState::REPLACE,
+            None,
+            &[],
+        );
+        let downsample_bind_group_layout =
+            Self::create_source_bind_group_layout(device, wgpu::TextureSampleType::Float { filterable: false }, 1);
+        let downsample_pipeline = GoLRenderer::create_pipeline(
+            device,
+            DENSITY_FORMAT,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&downsample_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("density_downsample.wgsl")),
+            wgpu::BlendState::REPLACE,
+            None,
+            &[],
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            base_bind_group_layout,
+            base_pipeline,
+            downsample_bind_group_layout,
+            downsample_pipeline,
+            bind_group_layout,
+            sampler,
+            texture: None,
+            bind_group: None,
+        }
+    }
+
+    /// Textures at bindings from 0
+    fn create_source_bind_group_layout(
+        device: &wgpu::Device,
+        sample_type: wgpu::TextureSampleType,
+        texture_count: u32,
+    ) -> wgpu::BindGroupLayout {
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = (0..texture_count)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    sample_type,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            })
+            .collect();
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &entries,
+        })
+    }
+
+    /// Layout of get_bind_group(), texture at binding 0 and sampler at binding 1
+    pub fn get_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Valid after the first update()
+    pub fn get_bind_group(&self) -> &wgpu::BindGroup {
+        self.bind_group.as_ref().expect("density pyramid is not built yet")
+    }
+
+    /// Rebuilds all levels from the current cells
+    pub fn update(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, gol: &GoL) {
+        let (width, height) = gol.get_size();
+        let size = wgpu::Extent3d {
+            width: (width / 2).max(1),
+            height: (height / 2).max(1),
+            depth_or_array_layers: 1,
+        };
+        if self.texture.as_ref().is_none_or(|texture| texture.size() != size) {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Density Pyramid"),
+                size,
+                mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DENSITY_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: None,
+                layout: &self.bind_group_layout,
+            }));
+            self.texture = Some(texture);
+        }
+        let texture = self.texture.as_ref().unwrap();
+        let level_views: Vec<wgpu::TextureView> = (0..texture.mip_level_count())
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        for (level, target) in level_views.iter().enumerate() {
+            let (pipeline, layout, sources) = if level == 0 {
+                (
+                    &self.base_pipeline,
+                    &self.base_bind_group_layout,
+                    vec![gol.get_current_view(), gol.get_previous_view()],
+                )
+            } else {
+                (&self.downsample_pipeline, &self.downsample_bind_group_layout, vec![&level_views[level - 1]])
+            };
+            let entries: Vec<wgpu::BindGroupEntry> = sources
+                .into_iter()
+                .enumerate()
+                .map(|(binding, source)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: wgpu::BindingResource::TextureView(source),
+                })
+                .collect();
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                entries: &entries,
+                label: None,
+                layout,
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Density Pyramid Render Pass"),
+                timestamp_writes: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    resolve_target: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, Some(&bind_group), &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+}
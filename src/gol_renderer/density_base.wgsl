@@ -0,0 +1,45 @@
+@group(0) @binding(0)
+var cells: texture_2d<u32>;
+@group(0) @binding(1)
+var previous_cells: texture_2d<u32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const full_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(full_quad[vertex_index], 0.0, 1.0);
+    return out;
+}
+
+// level 0 of the pyramid is half the grid size, so a texel covers 2x2 cells (3 on the last row or column
+// of odd sizes, like in density_downsample.wgsl). Any non-zero state counts as alive
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = vec2<u32>(input.clip_position.xy);
+    let grid_size = textureDimensions(cells);
+    let size = max(grid_size / 2u, vec2<u32>(1u));
+    let start = texel * grid_size / size;
+    let end = (texel + 1u) * grid_size / size;
+    var sum = vec3<f32>(0.0);
+    for (var y = start.y; y < end.y; y++) {
+        for (var x = start.x; x < end.x; x++) {
+            let alive = textureLoad(cells, vec2<u32>(x, y), 0).x != 0u;
+            let was_alive = textureLoad(previous_cells, vec2<u32>(x, y), 0).x != 0u;
+            sum += vec3<f32>(f32(alive), f32(alive && !was_alive), f32(!alive && was_alive));
+        }
+    }
+    let count = f32((end.x - start.x) * (end.y - start.y));
+    return vec4<f32>(sum / count, 1.0);
+}
@@ -0,0 +1,42 @@
+// view of the previous level only, the level being rendered can't be bound
+@group(0) @binding(0)
+var previous_level: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const full_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(full_quad[vertex_index], 0.0, 1.0);
+    return out;
+}
+
+// average of the texels this one covers. Mip sizes are rounded down, so with odd sizes
+// the last texel covers 3 texels of the previous level instead of 2 and no cell is lost
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = vec2<u32>(input.clip_position.xy);
+    let previous_size = textureDimensions(previous_level);
+    let size = max(previous_size / 2u, vec2<u32>(1u));
+    let start = texel * previous_size / size;
+    let end = (texel + 1u) * previous_size / size;
+    var sum = vec3<f32>(0.0);
+    for (var y = start.y; y < end.y; y++) {
+        for (var x = start.x; x < end.x; x++) {
+            sum += textureLoad(previous_level, vec2<u32>(x, y), 0).xyz;
+        }
+    }
+    let count = f32((end.x - start.x) * (end.y - start.y));
+    return vec4<f32>(sum / count, 1.0);
+}
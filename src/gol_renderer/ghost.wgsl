@@ -0,0 +1,58 @@
+struct GhostUniform {
+    view_proj: mat4x4<f32>,
+    quad_transform: mat4x4<f32>,
+    // x, y, width, height of the ghost in cells
+    rect: vec4<f32>,
+    // xy is grid size in cells
+    grid_size: vec4<f32>,
+    alive_color: vec4<f32>,
+    // tints the rest of the ghost rectangle, so its bounds are visible
+    dead_color: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> ghost: GhostUniform;
+@group(0) @binding(1)
+var pattern: texture_2d<u32>;
+
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+}
+
+const unit_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = unit_quad[input.vertex_index];
+    // same mapping as the grid quad: cell (0, 0) is at the top left corner, quad goes from -1 to 1
+    let grid_uv = (ghost.rect.xy + uv * ghost.rect.zw) / ghost.grid_size.xy;
+    let pos = vec2<f32>(grid_uv.x * 2.0 - 1.0, 1.0 - grid_uv.y * 2.0);
+    out.clip_position = ghost.view_proj * ghost.quad_transform * vec4<f32>(pos, 1.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let size = textureDimensions(pattern);
+    let cell = min(vec2<u32>(input.uv * vec2<f32>(size)), size - 1u);
+    let val = textureLoad(pattern, cell, 0).x;
+    if (val == 1) {
+        return ghost.alive_color;
+    } else {
+        return ghost.dead_color;
+    }
+}
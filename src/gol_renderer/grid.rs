@@ -0,0 +1,69 @@
+/// Lines between cells, blended over them. Alpha is the strongest the lines get when zoomed in
+const GRID_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.35];
+const MAJOR_GRID_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.6];
+pub const MAX_MAJOR_GRID_STEP: u32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CellShape {
+    Square,
+    Rounded,
+    Circle,
+}
+
+impl CellShape {
+    pub const ALL: [CellShape; 3] = [CellShape::Square, CellShape::Rounded, CellShape::Circle];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CellShape::Square => "Square",
+            CellShape::Rounded => "Rounded",
+            CellShape::Circle => "Circle",
+        }
+    }
+
+    /// Corner radius and gap between neighbours, both in cells
+    fn get_radius_and_gap(&self) -> (f32, f32) {
+        match self {
+            CellShape::Square => (0.0, 0.0),
+            CellShape::Rounded => (0.2, 0.06),
+            CellShape::Circle => (0.5, 0.06),
+        }
+    }
+}
+
+pub struct GridSettings {
+    pub is_visible: bool,
+    /// Every N-th line is thicker, 0 disables major lines
+    pub major_step: u32,
+    /// Shapes other than square are only drawn when a cell is several pixels big
+    pub cell_shape: CellShape,
+}
+
+impl GridSettings {
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            major_step: 10,
+            cell_shape: CellShape::Square,
+        }
+    }
+
+    pub(super) fn get_uniform(&self) -> GridUniform {
+        let (radius, gap) = self.cell_shape.get_radius_and_gap();
+        GridUniform {
+            settings: [self.is_visible as u32 as f32, self.major_step as f32, radius, gap],
+            color: GRID_COLOR,
+            major_color: MAJOR_GRID_COLOR,
+        }
+    }
+}
+
+/// Part of the grid shader uniform
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct GridUniform {
+    /// is visible, major step, cell corner radius, gap between cells
+    settings: [f32; 4],
+    color: [f32; 4],
+    major_color: [f32; 4],
+}
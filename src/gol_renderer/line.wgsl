@@ -0,0 +1,62 @@
+// same uniform as rect.wgsl
+struct RectUniform {
+    view_proj: mat4x4<f32>,
+    quad_transform: mat4x4<f32>,
+    // xy is grid size in cells
+    grid_size: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: RectUniform;
+
+// one instance per infinite line, it's drawn over the whole grid
+struct InstanceInput {
+    // point on the line in cells
+    @location(0) point: vec2<f32>,
+    // normalized
+    @location(1) direction: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // signed distance from the line in cells
+    @location(0) distance: f32,
+    @location(1) @interpolate(flat) color: vec4<f32>,
+}
+
+const unit_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let grid_uv = unit_quad[vertex_index];
+    let pos = vec2<f32>(grid_uv.x * 2.0 - 1.0, 1.0 - grid_uv.y * 2.0);
+    out.clip_position = camera.view_proj * camera.quad_transform * vec4<f32>(pos, 1.0, 1.0);
+    let offset = grid_uv * camera.grid_size.xy - instance.point;
+    // 2D cross product, distance is linear so it interpolates exactly
+    out.distance = offset.x * instance.direction.y - offset.y * instance.direction.x;
+    out.color = instance.color;
+
+    return out;
+}
+
+// line width in screen pixels, so it stays visible at any zoom
+const line_width: f32 = 1.5;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let distance_per_pixel = max(length(vec2<f32>(dpdx(input.distance), dpdy(input.distance))), 1e-6);
+    let pixels = abs(input.distance) / distance_per_pixel;
+    if (pixels > line_width) {
+        discard;
+    }
+    // a bit softer edge, diagonal lines would be jagged otherwise
+    return vec4<f32>(input.color.rgb, input.color.a * clamp(line_width - pixels + 0.5, 0.0, 1.0));
+}
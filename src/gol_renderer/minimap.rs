@@ -0,0 +1,210 @@
+use super::{GoLRenderer, Theme};
+use crate::gol::GoL;
+use egui_wgpu::wgpu;
+use glam::{vec2, Vec2};
+
+/// Visible area outline and its fill
+const VIEWPORT_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+/// Space between the minimap and the window edges, in physical pixels
+const MARGIN: f32 = 10.0;
+pub const MIN_MINIMAP_SIZE: u32 = 64;
+pub const MAX_MINIMAP_SIZE: u32 = 512;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl MinimapCorner {
+    pub const ALL: [MinimapCorner; 4] = [
+        MinimapCorner::TopLeft,
+        MinimapCorner::TopRight,
+        MinimapCorner::BottomLeft,
+        MinimapCorner::BottomRight,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MinimapCorner::TopLeft => "Top left",
+            MinimapCorner::TopRight => "Top right",
+            MinimapCorner::BottomLeft => "Bottom left",
+            MinimapCorner::BottomRight => "Bottom right",
+        }
+    }
+}
+
+pub struct MinimapSettings {
+    pub is_visible: bool,
+    /// Longer side in physical pixels, the other one follows the grid aspect ratio
+    pub size: u32,
+    pub corner: MinimapCorner,
+}
+
+impl MinimapSettings {
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            size: 200,
+            corner: MinimapCorner::BottomRight,
+        }
+    }
+
+    /// Minimap position on the screen as (x, y, width, height) in physical pixels. None when hidden or the window is too small
+    pub fn get_rect(&self, (screen_width, screen_height): (u32, u32), (grid_width, grid_height): (u32, u32)) -> Option<[f32; 4]> {
+        if !self.is_visible {
+            return None;
+        }
+        let longer_side = grid_width.max(grid_height) as f32;
+        let width = (self.size as f32 * grid_width as f32 / longer_side).round().max(1.0);
+        let height = (self.size as f32 * grid_height as f32 / longer_side).round().max(1.0);
+        let (screen_width, screen_height) = (screen_width as f32, screen_height as f32);
+        if width + 2.0 * MARGIN > screen_width || height + 2.0 * MARGIN > screen_height {
+            return None;
+        }
+        let x = match self.corner {
+            MinimapCorner::TopLeft | MinimapCorner::BottomLeft => MARGIN,
+            MinimapCorner::TopRight | MinimapCorner::BottomRight => screen_width - MARGIN - width,
+        };
+        let y = match self.corner {
+            MinimapCorner::TopLeft | MinimapCorner::TopRight => MARGIN,
+            MinimapCorner::BottomLeft | MinimapCorner::BottomRight => screen_height - MARGIN - height,
+        };
+        Some([x, y, width, height])
+    }
+
+    pub fn contains(&self, screen_size: (u32, u32), grid_size: (u32, u32), point: Vec2) -> bool {
+        self.get_rect(screen_size, grid_size).is_some_and(|[x, y, width, height]| {
+            point.x >= x && point.y >= y && point.x < x + width && point.y < y + height
+        })
+    }
+
+    /// Cell under the screen point (physical pixels), clamped to the grid so dragging can go past the minimap edges
+    pub fn screen_to_cell(&self, screen_size: (u32, u32), grid_size: (u32, u32), point: Vec2) -> Option<Vec2> {
+        let [x, y, width, height] = self.get_rect(screen_size, grid_size)?;
+        let uv = ((point - vec2(x, y)) / vec2(width, height)).clamp(Vec2::ZERO, Vec2::ONE);
+        Some(uv * vec2(grid_size.0 as f32, grid_size.1 as f32))
+    }
+}
+
+/// What the minimap shows this frame
+pub struct MinimapView {
+    /// x, y, width, height in physical pixels, see MinimapSettings::get_rect()
+    pub rect: [f32; 4],
+    /// Corners of the visible area in cells, in order around it
+    pub viewport: [Vec2; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MinimapUniform {
+    viewport_a: [f32; 4],
+    viewport_b: [f32; 4],
+    size: [f32; 4],
+    alive_color: [f32; 4],
+    dead_color: [f32; 4],
+    viewport_color: [f32; 4],
+}
+
+/// Density of the whole grid drawn in a corner of the screen, with the visible area outlined.
+/// It's sampled from the DensityPyramid at the level where one texel is about one minimap pixel
+pub struct Minimap {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl Minimap {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, density_bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    min_binding_size: None,
+                    has_dynamic_offset: false,
+                },
+                count: None,
+            }],
+        });
+        let pipeline = GoLRenderer::create_pipeline(
+            device,
+            format,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, density_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("minimap.wgsl")),
+            wgpu::BlendState::REPLACE,
+            None,
+            &[],
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<MinimapUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            bind_group_layout,
+            pipeline,
+            uniform_buffer,
+        }
+    }
+
+    /// Uploads the uniform and returns the bind group to draw the minimap with, see draw()
+    pub fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gol: &GoL,
+        view: &MinimapView,
+        theme: &Theme,
+    ) -> wgpu::BindGroup {
+        let (gol_width, gol_height) = gol.get_size();
+        let grid_size = vec2(gol_width as f32, gol_height as f32);
+        let [a, b, c, d] = view.viewport.map(|corner| corner / grid_size);
+        let (dead, alive) = (theme.get_color(0), theme.get_color(1));
+        let cells_per_pixel = (grid_size.x / view.rect[2]).max(grid_size.y / view.rect[3]);
+        let uniform = MinimapUniform {
+            viewport_a: [a.x, a.y, b.x, b.y],
+            viewport_b: [c.x, c.y, d.x, d.y],
+            size: [view.rect[2], view.rect[3], (cells_per_pixel.log2() - 1.0).max(0.0), 0.0],
+            alive_color: [alive[0], alive[1], alive[2], 1.0],
+            dead_color: [dead[0], dead[1], dead[2], 1.0],
+            viewport_color: VIEWPORT_COLOR,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.uniform_buffer.as_entire_binding(),
+            }],
+            label: None,
+            layout: &self.bind_group_layout,
+        })
+    }
+
+    /// Draws into the minimap rectangle, the viewport and scissor rect of the pass are changed
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        bind_group: &wgpu::BindGroup,
+        density_bind_group: &wgpu::BindGroup,
+        view: &MinimapView,
+    ) {
+        let [x, y, width, height] = view.rect;
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        // the pass may be clipped to a different viewport, see GoLRenderer::rerender_viewport()
+        render_pass.set_scissor_rect(x as u32, y as u32, width as u32, height as u32);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, Some(bind_group), &[]);
+        render_pass.set_bind_group(1, Some(density_bind_group), &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,89 @@
+struct MinimapUniform {
+    // corners of the visible area in grid uv, (0, 0) is the top left corner of the grid
+    viewport_a: vec4<f32>,
+    viewport_b: vec4<f32>,
+    // xy is the minimap size in screen pixels, z is the density pyramid level with about one texel per pixel
+    size: vec4<f32>,
+    alive_color: vec4<f32>,
+    dead_color: vec4<f32>,
+    viewport_color: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> minimap: MinimapUniform;
+@group(1) @binding(0)
+var density: texture_2d<f32>;
+@group(1) @binding(1)
+var density_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+const full_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+// drawn with the viewport set to the minimap rectangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let pos = full_quad[vertex_index];
+    out.clip_position = vec4<f32>(pos, 0.0, 1.0);
+    out.uv = vec2<f32>(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+    return out;
+}
+
+// line width in screen pixels
+const line_width: f32 = 1.5;
+
+// distance from p to the segment a-b
+fn segment_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let ab = b - a;
+    let t = clamp(dot(p - a, ab) / max(dot(ab, ab), 1e-6), 0.0, 1.0);
+    return length(p - a - ab * t);
+}
+
+fn cross_2d(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // sqrt makes sparse areas visible, a few gliders are a tiny fraction of their block
+    let alive = sqrt(textureSampleLevel(density, density_sampler, input.uv, minimap.size.z).x);
+    var color = mix(minimap.dead_color, minimap.alive_color, alive);
+
+    // visible area is a quad, it's rotated with the camera
+    let pixel = input.uv * minimap.size.xy;
+    let corners = array<vec2<f32>, 4>(
+        minimap.viewport_a.xy * minimap.size.xy,
+        minimap.viewport_a.zw * minimap.size.xy,
+        minimap.viewport_b.xy * minimap.size.xy,
+        minimap.viewport_b.zw * minimap.size.xy,
+    );
+    var distance = 1e6;
+    var inside_count = 0;
+    for (var i = 0; i < 4; i++) {
+        let a = corners[i];
+        let b = corners[(i + 1) % 4];
+        distance = min(distance, segment_distance(pixel, a, b));
+        inside_count += i32(sign(cross_2d(b - a, pixel - a)));
+    }
+    if (abs(inside_count) == 4) {
+        color = mix(color, minimap.viewport_color, 0.15);
+    }
+    color = mix(color, minimap.viewport_color, clamp(line_width - distance + 0.5, 0.0, 1.0));
+
+    // border around the whole minimap
+    let border_distance = min(min(pixel.x, pixel.y), min(minimap.size.x - pixel.x, minimap.size.y - pixel.y));
+    if (border_distance < 1.0) {
+        color = minimap.viewport_color * 0.5;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -1,13 +1,57 @@
+mod density;
+mod grid;
+mod minimap;
+mod offscreen;
+mod theme;
+mod trail;
+
+pub use grid::{CellShape, GridSettings, MAX_MAJOR_GRID_STEP};
+pub use minimap::{MinimapCorner, MinimapSettings, MinimapView, MAX_MINIMAP_SIZE, MIN_MINIMAP_SIZE};
+pub use offscreen::{get_whole_grid_transforms, read_texture};
+pub use theme::{load_all_themes, save_themes, Theme, DEFAULT_THEMES_PATH, MAX_STATES};
+pub use trail::{TrailSettings, MIN_TRAIL_DECAY};
 use crate::gol::GoL;
+use crate::gol_manager::patterns::Pattern;
+use crate::shader_reload::create_checked;
 use glam::{Mat3, Mat4, Vec2};
 use egui_wgpu::wgpu;
+use density::DensityPyramid;
+use grid::GridUniform;
+use minimap::Minimap;
+use trail::{Trail, TrailUniform};
 use wgpu::util::DeviceExt;
 
+/// Built-in shader drawing the cells, see GoLRenderer::set_shader() for custom ones
+pub const SHADER_SOURCE: &str = include_str!("shaders.wgsl");
+/// Pattern preview under the cursor, blended over the grid
+const GHOST_ALIVE_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 0.6];
+const GHOST_DEAD_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 0.12];
+pub const SELECTION_FILL_COLOR: [f32; 4] = [0.3, 0.7, 1.0, 0.08];
+pub const SELECTION_BORDER_COLOR: [f32; 4] = [0.3, 0.7, 1.0, 0.9];
+pub const GUIDE_COLOR: [f32; 4] = [1.0, 0.5, 0.1, 0.7];
+/// Changes in the last generation, see GoLRenderer::set_diff()
+const BIRTH_COLOR: [f32; 4] = [0.1, 0.85, 0.15, 1.0];
+const DEATH_COLOR: [f32; 4] = [0.9, 0.08, 0.05, 1.0];
+const SURVIVOR_COLOR: [f32; 4] = [0.4, 0.4, 0.45, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DiffUniform {
+    birth_color: [f32; 4],
+    death_color: [f32; 4],
+    survivor_color: [f32; 4],
+    /// x is 1 when the diff is shown instead of the theme colours
+    is_enabled: [f32; 4],
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct Uniform {
     view_proj: [[f32; 4]; 4],
     quad_transform: [[f32; 4]; 4],
+    grid: GridUniform,
+    trail: TrailUniform,
+    diff: DiffUniform,
 }
 
 impl Uniform {
@@ -15,6 +59,14 @@ impl Uniform {
         Self {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
             quad_transform: Mat4::IDENTITY.to_cols_array_2d(),
+            grid: GridSettings::new().get_uniform(),
+            trail: TrailSettings::new().get_uniform(),
+            diff: DiffUniform {
+                birth_color: BIRTH_COLOR,
+                death_color: DEATH_COLOR,
+                survivor_color: SURVIVOR_COLOR,
+                is_enabled: [0.0; 4],
+            },
         }
     }
     fn update(&mut self, view_proj: Mat4, quad_transform: Mat4) {
@@ -23,11 +75,87 @@ impl Uniform {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GhostUniform {
+    view_proj: [[f32; 4]; 4],
+    quad_transform: [[f32; 4]; 4],
+    /// x, y, width, height in cells
+    rect: [f32; 4],
+    grid_size: [f32; 4],
+    alive_color: [f32; 4],
+    dead_color: [f32; 4],
+}
+
+/// Rectangle drawn over the grid, e.g. selection. Colors are linear with alpha
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RectOverlay {
+    /// x, y, width, height in cells
+    pub rect: [f32; 4],
+    pub fill_color: [f32; 4],
+    /// Border is a few screen pixels wide at any zoom
+    pub border_color: [f32; 4],
+}
+
+/// Infinite line drawn over the grid, e.g. symmetry axis. It's a few screen pixels wide at any zoom
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineOverlay {
+    /// Any point on the line, in cells
+    pub point: [f32; 2],
+    /// Normalized
+    pub direction: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Shared by rect and line overlays
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct RectUniform {
+    view_proj: [[f32; 4]; 4],
+    quad_transform: [[f32; 4]; 4],
+    grid_size: [f32; 4],
+}
+
 pub struct GoLRenderer {
+    /// Of the render target, pipelines are created for it
+    format: wgpu::TextureFormat,
     bind_group_layout: wgpu::BindGroupLayout,
+    /// Colour lookup table of the theme, uploaded only when the theme changes
+    palette_texture: wgpu::Texture,
+    palette_view: wgpu::TextureView,
+    /// None until set_theme() is called
+    theme: Option<Theme>,
     pipeline: wgpu::RenderPipeline,
+    /// Kept to recreate the pipeline with another shader, see set_shader()
+    main_pipeline_layout: wgpu::PipelineLayout,
     uniform_buffer: wgpu::Buffer,
     uniform: Uniform,
+    /// Fills a viewport with the background colour, see rerender_viewport()
+    background_pipeline: wgpu::RenderPipeline,
+    ghost_pipeline: wgpu::RenderPipeline,
+    ghost_uniform_buffer: wgpu::Buffer,
+    /// Armed pattern, uploaded only when it changes
+    ghost_texture: Option<wgpu::Texture>,
+    /// Top left cell of the ghost, None hides it
+    ghost_position: Option<(i64, i64)>,
+    rect_bind_group_layout: wgpu::BindGroupLayout,
+    rect_pipeline: wgpu::RenderPipeline,
+    rect_uniform_buffer: wgpu::Buffer,
+    /// One instance per overlay, see upload_instances()
+    rect_instance_buffer: Option<wgpu::Buffer>,
+    rect_overlays: Vec<RectOverlay>,
+    line_pipeline: wgpu::RenderPipeline,
+    line_instance_buffer: Option<wgpu::Buffer>,
+    line_overlays: Vec<LineOverlay>,
+    /// Used when zoomed out and by the minimap
+    density_pyramid: DensityPyramid,
+    /// Drawn under the live cells
+    trail: Trail,
+    minimap: Minimap,
+    /// None hides the minimap
+    minimap_view: Option<MinimapView>,
 }
 
 impl GoLRenderer {
@@ -40,7 +168,7 @@ impl GoLRenderer {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         min_binding_size: None,
@@ -58,34 +186,237 @@ impl GoLRenderer {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     count: None,
                 },
+                // colour of every cell value, MAX_STATES x 1
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    count: None,
+                },
+                // cells before the last generation, for the diff
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    count: None,
+                },
             ],
         });
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Palette"),
+            size: wgpu::Extent3d {
+                width: MAX_STATES as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let camera_uniform = Uniform::new();
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             contents: bytemuck::cast_slice(&[camera_uniform]),
         });
-        let shader_module = device.create_shader_module(wgpu::include_wgsl!("shaders.wgsl"));
+        let density_pyramid = DensityPyramid::new(device);
+        let trail = Trail::new(device);
+        let main_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &bind_group_layout,
+                density_pyramid.get_bind_group_layout(),
+                trail.get_bind_group_layout(),
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = Self::create_main_pipeline(device, format, &main_pipeline_layout, SHADER_SOURCE);
+
+        // uses the same layout, uniform and texture with cells
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let ghost_shader_module = device.create_shader_module(wgpu::include_wgsl!("ghost.wgsl"));
+        let ghost_pipeline = Self::create_pipeline(
+            device,
+            format,
+            &pipeline_layout,
+            &ghost_shader_module,
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
+            &[],
+        );
+        let ghost_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<GhostUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let rect_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    min_binding_size: None,
+                    has_dynamic_offset: false,
+                },
+                count: None,
+            }],
+        });
+        let rect_shader_module = device.create_shader_module(wgpu::include_wgsl!("rect.wgsl"));
+        let rect_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&rect_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let rect_pipeline = Self::create_pipeline(
+            device,
+            format,
+            &rect_pipeline_layout,
+            &rect_shader_module,
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<RectOverlay>() as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4],
+            }],
+        );
+        let line_shader_module = device.create_shader_module(wgpu::include_wgsl!("line.wgsl"));
+        let line_pipeline = Self::create_pipeline(
+            device,
+            format,
+            &rect_pipeline_layout,
+            &line_shader_module,
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<LineOverlay>() as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
+            }],
+        );
+        // blend constant replaces whatever the shader writes
+        let constant_blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let background_pipeline = Self::create_pipeline(
+            device,
+            format,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("background.wgsl")),
+            wgpu::BlendState {
+                color: constant_blend,
+                alpha: constant_blend,
+            },
+            None,
+            &[],
+        );
+        let rect_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<RectUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            format,
+            bind_group_layout,
+            palette_texture,
+            palette_view,
+            theme: None,
+            pipeline,
+            main_pipeline_layout,
+            uniform_buffer: camera_buffer,
+            uniform: camera_uniform,
+            background_pipeline,
+            ghost_pipeline,
+            ghost_uniform_buffer,
+            ghost_texture: None,
+            ghost_position: None,
+            rect_bind_group_layout,
+            rect_pipeline,
+            rect_uniform_buffer,
+            rect_instance_buffer: None,
+            rect_overlays: Vec::new(),
+            line_pipeline,
+            line_instance_buffer: None,
+            line_overlays: Vec::new(),
+            minimap: Minimap::new(device, format, density_pyramid.get_bind_group_layout()),
+            density_pyramid,
+            trail,
+            minimap_view: None,
+        }
+    }
+
+    fn create_main_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        layout: &wgpu::PipelineLayout,
+        source: &str,
+    ) -> wgpu::RenderPipeline {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        Self::create_pipeline(device, format, layout, &shader_module, wgpu::BlendState::REPLACE, Some(wgpu::Face::Back), &[])
+    }
+
+    /// Replaces the shader drawing the cells, e.g. with one edited on disk. The bind groups stay the same,
+    /// the current pipeline is kept when the new one doesn't compile
+    pub fn set_shader(&mut self, device: &wgpu::Device, source: &str) -> Result<(), String> {
+        self.pipeline = create_checked(device, || {
+            Self::create_main_pipeline(device, self.format, &self.main_pipeline_layout, source)
+        })?;
+        Ok(())
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        blend: wgpu::BlendState,
+        cull_mode: Option<wgpu::Face>,
+        buffers: &[wgpu::VertexBufferLayout],
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             multiview: None,
             fragment: Some(wgpu::FragmentState {
-                module: &shader_module,
+                module: shader_module,
                 entry_point: Some("fs_main"),
                 compilation_options: Default::default(),
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
                     write_mask: wgpu::ColorWrites::ALL,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    blend: Some(blend),
                 })],
             }),
-            layout: Some(&pipeline_layout),
+            layout: Some(layout),
             cache: None,
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
@@ -99,28 +430,142 @@ impl GoLRenderer {
                 strip_index_format: None,
                 polygon_mode: wgpu::PolygonMode::Fill,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
+                cull_mode,
                 unclipped_depth: false,
             },
             vertex: wgpu::VertexState {
                 compilation_options: Default::default(),
                 entry_point: Some("vs_main"),
-                module: &shader_module,
-                buffers: &[],
+                module: shader_module,
+                buffers,
             },
-        });
+        })
+    }
 
-        Self {
-            bind_group_layout,
-            pipeline,
-            uniform_buffer: camera_buffer,
-            uniform: camera_uniform,
+    /// Pattern drawn translucent over the grid, e.g. to preview where it will be stamped. None removes it
+    pub fn set_ghost_pattern(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pattern: Option<&Pattern>) {
+        // pattern that doesn't fit into a texture is still stamped, it just has no ghost
+        let max_side = device.limits().max_texture_dimension_2d;
+        let Some(pattern) = pattern.filter(|pattern| {
+            pattern.width > 0 && pattern.height > 0 && pattern.width <= max_side && pattern.height <= max_side
+        }) else {
+            self.ghost_texture = None;
+            return;
+        };
+        let size = wgpu::Extent3d {
+            width: pattern.width,
+            height: pattern.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = match self.ghost_texture.take() {
+            Some(texture) if texture.size() == size => texture,
+            _ => device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Uint,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            }),
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+                mip_level: 0,
+            },
+            &pattern.data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(pattern.width),
+                rows_per_image: Some(pattern.height),
+            },
+            size,
+        );
+        self.ghost_texture = Some(texture);
+    }
+
+    /// Top left cell of the ghost pattern, can be outside of the grid
+    pub fn set_ghost_position(&mut self, position: Option<(i64, i64)>) {
+        self.ghost_position = position;
+    }
+
+    /// Replaces rectangles drawn over the grid, they are drawn in order on top of the ghost
+    pub fn set_rect_overlays(&mut self, overlays: Vec<RectOverlay>) {
+        self.rect_overlays = overlays;
+    }
+
+    /// Replaces lines drawn over the grid, they are drawn on top of the rectangles
+    pub fn set_line_overlays(&mut self, overlays: Vec<LineOverlay>) {
+        self.line_overlays = overlays;
+    }
+
+    /// Uploads the colour lookup table if the theme differs from the current one. Returns true when it did
+    pub fn set_theme(&mut self, queue: &wgpu::Queue, theme: &Theme) -> bool {
+        if self.theme.as_ref() == Some(theme) {
+            return false;
         }
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.palette_texture,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+                mip_level: 0,
+            },
+            bytemuck::cast_slice(&theme.get_lookup_table()),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(MAX_STATES as u32 * 16),
+                rows_per_image: Some(1),
+            },
+            self.palette_texture.size(),
+        );
+        self.theme = Some(theme.clone());
+        true
+    }
+
+    /// Grid lines and cell shape, uploaded with the camera in rerender()
+    pub fn set_grid(&mut self, settings: &GridSettings) {
+        self.uniform.grid = settings.get_uniform();
+    }
+
+    /// Colours cells by what happened to them in the last generation (born, died or survived) instead of by the theme
+    pub fn set_diff(&mut self, is_enabled: bool) {
+        self.uniform.diff.is_enabled[0] = is_enabled as u32 as f32;
+    }
+
+    pub fn set_trail(&mut self, settings: &TrailSettings) {
+        self.uniform.trail = settings.get_uniform();
+        self.trail.set_settings(settings);
+    }
+
+    /// Has to be called after every GoL::update(), does nothing when the trail is off
+    pub fn update_trail(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL) {
+        self.trail.update(device, queue, gol);
+    }
+
+    /// Minimap drawn over everything else, None hides it
+    pub fn set_minimap(&mut self, view: Option<MinimapView>) {
+        self.minimap_view = view;
+    }
+
+    /// Rebuilds the density pyramid that zoomed out views and the minimap are drawn from. Has to be called once
+    /// before drawing a frame (or an image) in which the cells changed, all views of the frame share it
+    pub fn update_density(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Density Encoder"),
+        });
+        self.density_pyramid.update(device, &mut encoder, gol);
+        queue.submit(Some(encoder.finish()));
     }
 
     /// To allow navigation and scrolling and dimension flexibility, i create a quad to which i render game of life
     /// Here you can pass transform matrix to move and scale the quad
     /// By default quad is at origin and has radius 1 (-1 to 1 in x and y)
+    #[allow(clippy::too_many_arguments)]
     pub fn rerender(
         &mut self,
         device: &wgpu::Device,
@@ -130,6 +575,41 @@ impl GoLRenderer {
         target_view: &wgpu::TextureView,
         view_proj: Mat3,
         quad_transform: Mat3,
+    ) {
+        self.draw(device, queue, encoder, gol, target_view, view_proj, quad_transform, None, true);
+    }
+
+    /// Same as rerender() but only into the rect (x, y, width, height in pixels) of the target, the rest is kept.
+    /// view_proj is for the rect, so its aspect ratio should match. Uniforms are shared by all calls, so the encoder
+    /// has to be submitted before the next one
+    #[allow(clippy::too_many_arguments)]
+    pub fn rerender_viewport(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        gol: &GoL,
+        target_view: &wgpu::TextureView,
+        view_proj: Mat3,
+        quad_transform: Mat3,
+        rect: [u32; 4],
+    ) {
+        self.draw(device, queue, encoder, gol, target_view, view_proj, quad_transform, Some(rect), true);
+    }
+
+    /// Without overlays only the cells are drawn (with trail, grid lines and diff), e.g. for images, see render_image()
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        gol: &GoL,
+        target_view: &wgpu::TextureView,
+        view_proj: Mat3,
+        quad_transform: Mat3,
+        viewport: Option<[u32; 4]>,
+        with_overlays: bool,
     ) {
         self.uniform.update(Mat4::from_mat3(view_proj), Mat4::from_mat3(quad_transform));
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
@@ -146,19 +626,44 @@ impl GoLRenderer {
                         gol.get_current_view(),
                     ),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(gol.get_previous_view()),
+                },
             ],
             label: None,
             layout: &self.bind_group_layout,
         });
+        let trail_bind_group = self.trail.get_bind_group(device, gol);
+        let [r, g, b] = self.get_theme().background.map(|c| c as f64);
+        let background = wgpu::Color { r, g, b, a: 1.0 };
+        let (ghost_bind_group, overlay_bind_group, minimap_bind_group) = if with_overlays {
+            (
+                self.create_ghost_bind_group(device, queue, gol, view_proj, quad_transform),
+                self.prepare_overlays(device, queue, gol, view_proj, quad_transform),
+                self.minimap_view
+                    .as_ref()
+                    .map(|view| self.minimap.prepare(device, queue, gol, view, self.get_theme())),
+            )
+        } else {
+            (None, None, None)
+        };
 
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 timestamp_writes: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &target_view,
+                    view: target_view,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: match viewport {
+                            Some(_) => wgpu::LoadOp::Load,
+                            None => wgpu::LoadOp::Clear(background),
+                        },
                         store: wgpu::StoreOp::Store,
                     },
                     resolve_target: None,
@@ -166,10 +671,143 @@ impl GoLRenderer {
                 depth_stencil_attachment: None,
                 occlusion_query_set: None,
             });
+            if let Some([x, y, width, height]) = viewport {
+                render_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+                render_pass.set_scissor_rect(x, y, width, height);
+                render_pass.set_pipeline(&self.background_pipeline);
+                render_pass.set_blend_constant(background);
+                render_pass.draw(0..6, 0..1);
+            }
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(0, Some(&bind_group), &[]);
+            render_pass.set_bind_group(1, Some(self.density_pyramid.get_bind_group()), &[]);
+            render_pass.set_bind_group(2, Some(&trail_bind_group), &[]);
             render_pass.draw(0..6, 0..1);
+
+            if let Some(ghost_bind_group) = &ghost_bind_group {
+                render_pass.set_pipeline(&self.ghost_pipeline);
+                render_pass.set_bind_group(0, Some(ghost_bind_group), &[]);
+                render_pass.draw(0..6, 0..1);
+            }
+
+            if let Some(overlay_bind_group) = &overlay_bind_group {
+                let overlays = [
+                    (&self.rect_pipeline, &self.rect_instance_buffer, self.rect_overlays.len()),
+                    (&self.line_pipeline, &self.line_instance_buffer, self.line_overlays.len()),
+                ];
+                for (pipeline, instance_buffer, count) in overlays {
+                    let Some(instance_buffer) = instance_buffer.as_ref().filter(|_| count > 0) else {
+                        continue;
+                    };
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(0, Some(overlay_bind_group), &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                    render_pass.draw(0..6, 0..count as u32);
+                }
+            }
+
+            if let (Some(bind_group), Some(view)) = (&minimap_bind_group, &self.minimap_view) {
+                self.minimap.draw(&mut render_pass, bind_group, self.density_pyramid.get_bind_group(), view);
+            }
+        }
+    }
+
+    /// Uploads rect and line instances and the uniform they share
+    fn prepare_overlays(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gol: &GoL,
+        view_proj: Mat3,
+        quad_transform: Mat3,
+    ) -> Option<wgpu::BindGroup> {
+        if self.rect_overlays.is_empty() && self.line_overlays.is_empty() {
+            return None;
+        }
+        let (gol_width, gol_height) = gol.get_size();
+        let uniform = RectUniform {
+            view_proj: Mat4::from_mat3(view_proj).to_cols_array_2d(),
+            quad_transform: Mat4::from_mat3(quad_transform).to_cols_array_2d(),
+            grid_size: [gol_width as f32, gol_height as f32, 0.0, 0.0],
+        };
+        queue.write_buffer(&self.rect_uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        Self::upload_instances(device, queue, &mut self.rect_instance_buffer, bytemuck::cast_slice(&self.rect_overlays));
+        Self::upload_instances(device, queue, &mut self.line_instance_buffer, bytemuck::cast_slice(&self.line_overlays));
+
+        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.rect_uniform_buffer.as_entire_binding(),
+            }],
+            label: None,
+            layout: &self.rect_bind_group_layout,
+        }))
+    }
+
+    /// Buffer grows when there are more instances than it fits
+    fn upload_instances(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &mut Option<wgpu::Buffer>, instances: &[u8]) {
+        if instances.is_empty() {
+            return;
+        }
+        if buffer.as_ref().is_none_or(|buffer| buffer.size() < instances.len() as u64) {
+            *buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: instances.len().next_power_of_two() as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
         }
+        queue.write_buffer(buffer.as_ref().unwrap(), 0, instances);
+    }
+
+    fn create_ghost_bind_group(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gol: &GoL,
+        view_proj: Mat3,
+        quad_transform: Mat3,
+    ) -> Option<wgpu::BindGroup> {
+        let (texture, (x, y)) = (self.ghost_texture.as_ref()?, self.ghost_position?);
+        let (gol_width, gol_height) = gol.get_size();
+        let uniform = GhostUniform {
+            view_proj: Mat4::from_mat3(view_proj).to_cols_array_2d(),
+            quad_transform: Mat4::from_mat3(quad_transform).to_cols_array_2d(),
+            rect: [x as f32, y as f32, texture.width() as f32, texture.height() as f32],
+            grid_size: [gol_width as f32, gol_height as f32, 0.0, 0.0],
+            alive_color: GHOST_ALIVE_COLOR,
+            dead_color: GHOST_DEAD_COLOR,
+        };
+        queue.write_buffer(&self.ghost_uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.ghost_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                // not used by the ghost shader, the layout is shared with the grid
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+            label: None,
+            layout: &self.bind_group_layout,
+        }))
+    }
+
+    fn get_theme(&self) -> &Theme {
+        self.theme.as_ref().expect("set_theme() has to be called before rendering")
     }
 
     pub fn ndc_to_gol_uv(ndc: Vec2, view_proj: Mat3, quad_transform: Mat3) -> Vec2 {
@@ -0,0 +1,127 @@
+use super::GoLRenderer;
+use crate::gol::GoL;
+use egui_wgpu::wgpu;
+use glam::{vec2, Mat3};
+
+/// Bigger images are rendered in tiles, the device limit can make tiles smaller
+const MAX_TILE_SIZE: u32 = 4096;
+
+impl GoLRenderer {
+    /// Cells as they would look on a screen of the given size, RGBA rows from the top. Overlays and the minimap are left out.
+    /// Images bigger than a texture can be are rendered tile by tile, each with the projection narrowed to its part of the image
+    #[allow(clippy::too_many_arguments)]
+    pub async fn render_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gol: &GoL,
+        view_proj: Mat3,
+        quad_transform: Mat3,
+        (width, height): (u32, u32),
+    ) -> Vec<u8> {
+        let tile_size = device.limits().max_texture_dimension_2d.min(MAX_TILE_SIZE);
+        let mut image = vec![0; width as usize * height as usize * 4];
+        self.update_density(device, queue, gol);
+        for tile_y in (0..height).step_by(tile_size as usize) {
+            for tile_x in (0..width).step_by(tile_size as usize) {
+                let tile_width = tile_size.min(width - tile_x);
+                let tile_height = tile_size.min(height - tile_y);
+                // part of the image in ndc, y goes up
+                let min = vec2(
+                    tile_x as f32 / width as f32 * 2.0 - 1.0,
+                    1.0 - (tile_y + tile_height) as f32 / height as f32 * 2.0,
+                );
+                let max = vec2(
+                    (tile_x + tile_width) as f32 / width as f32 * 2.0 - 1.0,
+                    1.0 - tile_y as f32 / height as f32 * 2.0,
+                );
+                let tile_transform = Mat3::from_scale(2.0 / (max - min)) * Mat3::from_translation(-(min + max) / 2.0);
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Image Tile"),
+                    size: wgpu::Extent3d {
+                        width: tile_width,
+                        height: tile_height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Image Encoder") });
+                self.draw(device, queue, &mut encoder, gol, &view, tile_transform * view_proj, quad_transform, None, false);
+                queue.submit(Some(encoder.finish()));
+
+                let tile = read_texture(device, queue, &texture).await;
+                let row_size = tile_width as usize * 4;
+                for (row, pixels) in tile.chunks(row_size).enumerate() {
+                    let start = ((tile_y as usize + row) * width as usize + tile_x as usize) * 4;
+                    image[start..start + row_size].copy_from_slice(pixels);
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Camera and quad transform that show the whole grid with square cells, for images without a camera (e.g. headless)
+pub fn get_whole_grid_transforms((grid_width, grid_height): (u32, u32), (width, height): (u32, u32)) -> (Mat3, Mat3) {
+    let grid_aspect_ratio = grid_width as f32 / grid_height as f32;
+    let aspect_ratio = width as f32 / height as f32;
+    let quad_transform = Mat3::from_scale(vec2(grid_aspect_ratio, 1.0));
+    // shrinks the grid when it's relatively wider than the image
+    let fit = (aspect_ratio / grid_aspect_ratio).min(1.0);
+    let view_proj = Mat3::from_scale(vec2(fit / aspect_ratio, fit));
+    (view_proj, quad_transform)
+}
+
+/// Reads a texture with 8 bit RGBA or BGRA pixels as RGBA rows from the top. Row padding of the copy is stripped
+pub async fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Vec<u8> {
+    let (width, height) = (texture.width(), texture.height());
+    let row_size = width * 4;
+    let padded_row_size = row_size.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Read Texture Buffer"),
+        size: (padded_row_size * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Read Texture Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            layout: wgpu::ImageDataLayout {
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(height),
+                offset: 0,
+            },
+            buffer: &buffer,
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let buffer_slice = buffer.slice(..);
+    let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    receiver.receive().await.unwrap().unwrap();
+
+    let data = buffer_slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((row_size * height) as usize);
+    for row in data.chunks(padded_row_size as usize) {
+        pixels.extend_from_slice(&row[..row_size as usize]);
+    }
+    if matches!(texture.format(), wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    pixels
+}
@@ -0,0 +1,63 @@
+struct RectUniform {
+    view_proj: mat4x4<f32>,
+    quad_transform: mat4x4<f32>,
+    // xy is grid size in cells
+    grid_size: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: RectUniform;
+
+// one instance per rectangle
+struct InstanceInput {
+    // x, y, width, height in cells
+    @location(0) rect: vec4<f32>,
+    @location(1) fill_color: vec4<f32>,
+    @location(2) border_color: vec4<f32>,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // position inside the rectangle in cells
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) size: vec2<f32>,
+    @location(2) @interpolate(flat) fill_color: vec4<f32>,
+    @location(3) @interpolate(flat) border_color: vec4<f32>,
+}
+
+const unit_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = unit_quad[vertex_index];
+    // same mapping as the grid quad: cell (0, 0) is at the top left corner, quad goes from -1 to 1
+    let grid_uv = (instance.rect.xy + uv * instance.rect.zw) / camera.grid_size.xy;
+    let pos = vec2<f32>(grid_uv.x * 2.0 - 1.0, 1.0 - grid_uv.y * 2.0);
+    out.clip_position = camera.view_proj * camera.quad_transform * vec4<f32>(pos, 1.0, 1.0);
+    out.local = uv * instance.rect.zw;
+    out.size = instance.rect.zw;
+    out.fill_color = instance.fill_color;
+    out.border_color = instance.border_color;
+
+    return out;
+}
+
+// border width in screen pixels, so it stays visible at any zoom
+const border_width: f32 = 1.5;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let pixels_per_cell = 1.0 / max(fwidth(input.local), vec2<f32>(1e-6));
+    let distance = min(input.local, input.size - input.local) * pixels_per_cell;
+    if (min(distance.x, distance.y) < border_width) {
+        return input.border_color;
+    }
+    return input.fill_color;
+}
@@ -1,11 +1,44 @@
+struct GridUniform {
+    // x is 1 when lines are visible, y is the major line step (0 for none), z is the cell corner radius, w the gap between cells
+    settings: vec4<f32>,
+    color: vec4<f32>,
+    major_color: vec4<f32>,
+}
+struct DiffUniform {
+    birth_color: vec4<f32>,
+    death_color: vec4<f32>,
+    survivor_color: vec4<f32>,
+    // x is 1 when cells are coloured by what happened to them in the last generation
+    is_enabled: vec4<f32>,
+}
 struct CameraUniform {
     view_proj: mat4x4<f32>,
-    quad_transform: mat4x4<f32>
+    quad_transform: mat4x4<f32>,
+    grid: GridUniform,
+    // w is 1 when the trail is drawn
+    trail_color: vec4<f32>,
+    diff: DiffUniform,
 }
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 @group(0) @binding(1)
 var tex: texture_2d<u32>;
+// colour of every cell value, see Theme
+@group(0) @binding(2)
+var palette: texture_2d<f32>;
+// cells before the last generation
+@group(0) @binding(3)
+var previous_tex: texture_2d<u32>;
+// fraction of alive, born and died cells, see DensityPyramid
+@group(1) @binding(0)
+var density: texture_2d<f32>;
+@group(1) @binding(1)
+var density_sampler: sampler;
+// how recently cells were alive, see Trail
+@group(2) @binding(0)
+var trail: texture_2d<f32>;
+@group(2) @binding(1)
+var trail_sampler: sampler;
 
 struct VertexInput {
     @builtin(vertex_index) vertex_index: u32
@@ -37,14 +70,82 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     return out;
 }
 
+// how much of the pixel is covered by the line every `step` cells. Line is on the top and left side of the cell,
+// so it's exactly one pixel row when cell edges are on pixel edges
+fn line_coverage(cell: vec2<f32>, step: f32, cells_per_pixel: f32, width: f32) -> f32 {
+    let pixels_after_line = fract(cell / step) * step / cells_per_pixel;
+    return clamp(width + 0.5 - min(pixels_after_line.x, pixels_after_line.y), 0.0, 1.0);
+}
+
+// signed distance in cells from the point (relative to the cell center) to a rounded square filling the cell
+fn cell_distance(local: vec2<f32>, radius: f32, gap: f32) -> f32 {
+    let half_size = 0.5 - gap;
+    let corner = min(radius, half_size);
+    let q = abs(local) - vec2<f32>(half_size - corner);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - corner;
+}
+
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    // since texture is u32, need to use integer pixel uv instead of float
-    let base_uv = vec2<i32>(input.uv * vec2<f32>(textureDimensions(tex)));
-    let val = textureLoad(tex, base_uv, 0).x;
-    if (val == 1) {
-        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    let cell = input.uv * vec2<f32>(textureDimensions(tex));
+    // derivatives have to be taken before branching
+    let cells_per_pixel = max(length(dpdx(cell)), length(dpdy(cell)));
+    let pixels_per_cell = 1.0 / cells_per_pixel;
+    // trail is under the live cells, so it replaces the dead colour
+    var dead_color = textureLoad(palette, vec2<u32>(0u, 0u), 0);
+    let trail_amount = textureSampleLevel(trail, trail_sampler, input.uv, 0.0).x * camera.trail_color.w;
+    let is_diff = camera.diff.is_enabled.x > 0.0;
+    var color: vec4<f32>;
+    if (cells_per_pixel > 1.0) {
+        // zoomed out, a single cell would stand for all cells under the pixel and the image would shimmer
+        dead_color = mix(dead_color, vec4<f32>(camera.trail_color.rgb, 1.0), trail_amount);
+        let densities = textureSampleLevel(density, density_sampler, input.uv, log2(cells_per_pixel) - 1.0).xyz;
+        let alive = densities.x;
+        if (is_diff) {
+            let born = densities.y;
+            let died = densities.z;
+            color = dead_color * max(1.0 - alive - died, 0.0) + camera.diff.survivor_color * max(alive - born, 0.0)
+                + camera.diff.birth_color * born + camera.diff.death_color * died;
+        } else {
+            color = mix(dead_color, textureLoad(palette, vec2<u32>(1u, 0u), 0), alive);
+        }
     } else {
-        return vec4<f32>(0.005, 0.005, 0.005, 1.0);
+        // since texture is u32, need to use integer pixel uv instead of float
+        let val = textureLoad(tex, vec2<i32>(cell), 0).x;
+        let cell_trail = textureLoad(trail, vec2<i32>(cell), 0).x * camera.trail_color.w;
+        dead_color = mix(dead_color, vec4<f32>(camera.trail_color.rgb, 1.0), cell_trail);
+        color = select(textureLoad(palette, vec2<u32>(val, 0u), 0), dead_color, val == 0u);
+        var is_filled = val != 0u;
+        if (is_diff) {
+            let was_alive = textureLoad(previous_tex, vec2<i32>(cell), 0).x != 0u;
+            if (is_filled) {
+                color = select(camera.diff.birth_color, camera.diff.survivor_color, was_alive);
+            } else if (was_alive) {
+                color = camera.diff.death_color;
+                is_filled = true;
+            }
+        }
+        let gap = camera.grid.settings.w;
+        if (is_filled && gap > 0.0) {
+            let distance = cell_distance(fract(cell) - 0.5, camera.grid.settings.z, gap);
+            // shapes turn into plain squares when cells are too small to show them
+            let shape_amount = smoothstep(2.0, 6.0, pixels_per_cell);
+            let coverage = mix(1.0, clamp(0.5 - distance * pixels_per_cell, 0.0, 1.0), shape_amount);
+            color = mix(dead_color, color, coverage);
+        }
+    }
+
+    if (camera.grid.settings.x > 0.0) {
+        // lines fade in once there's enough space between them
+        let major_step = camera.grid.settings.y;
+        var major = 0.0;
+        if (major_step > 0.0) {
+            major = line_coverage(cell, major_step, cells_per_pixel, 2.0) * smoothstep(4.0, 8.0, major_step * pixels_per_cell);
+        }
+        // major lines are drawn instead of minor ones, not over them
+        let minor = line_coverage(cell, 1.0, cells_per_pixel, 1.0) * smoothstep(4.0, 8.0, pixels_per_cell) * (1.0 - major);
+        color = mix(color, vec4<f32>(camera.grid.color.rgb, 1.0), minor * camera.grid.color.a);
+        color = mix(color, vec4<f32>(camera.grid.major_color.rgb, 1.0), major * camera.grid.major_color.a);
     }
-}
\ No newline at end of file
+    return color;
+}
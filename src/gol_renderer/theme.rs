@@ -0,0 +1,166 @@
+use std::path::Path;
+
+/// Size of the colour lookup table, cell values are u8
+pub const MAX_STATES: usize = 256;
+pub const DEFAULT_THEMES_PATH: &str = "themes.txt";
+
+/// Colours of cell states. Colours are linear, surface is sRGB so they get converted on write
+#[derive(Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Index is the cell value, 0 is dead and 1 is alive. Values past the end use the last colour
+    pub colors: Vec<[f32; 3]>,
+    /// Outside of the grid
+    pub background: [f32; 3],
+}
+
+impl Theme {
+    /// Classic is first and is the default
+    pub fn built_in() -> Vec<Theme> {
+        vec![
+            Theme {
+                name: "Classic".to_string(),
+                colors: vec![[0.005; 3], [1.0; 3]],
+                background: [0.0; 3],
+            },
+            Theme::from_hex("Dark", 0x0b0d10, &[0x15181d, 0x8fbcbb, 0x88c0d0, 0xb48ead]),
+            Theme::from_hex("High contrast", 0x404040, &[0x000000, 0xffff00, 0x00ffff, 0xff00ff]),
+            // Okabe-Ito palette, distinguishable with all common colour vision deficiencies
+            Theme::from_hex(
+                "Colour-blind safe",
+                0x202020,
+                &[0x000000, 0xe69f00, 0x56b4e9, 0x009e73, 0xf0e442, 0x0072b2, 0xd55e00, 0xcc79a7],
+            ),
+        ]
+    }
+
+    fn from_hex(name: &str, background: u32, colors: &[u32]) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: colors.iter().map(|color| hex_to_linear(*color)).collect(),
+            background: hex_to_linear(background),
+        }
+    }
+
+    pub fn get_color(&self, state: usize) -> [f32; 3] {
+        self.colors[state.min(self.colors.len() - 1)]
+    }
+
+    /// Colour of every possible cell value, uploaded as a lookup texture
+    pub fn get_lookup_table(&self) -> Vec<[f32; 4]> {
+        (0..MAX_STATES).map(|state| {
+            let [r, g, b] = self.get_color(state);
+            [r, g, b, 1.0]
+        }).collect()
+    }
+
+    /// sRGB colours for indexed images, same conversion the GPU does when writing to the surface
+    pub fn get_srgb_colors(&self) -> Vec<[u8; 3]> {
+        self.colors.iter().map(|color| linear_to_srgb(*color)).collect()
+    }
+}
+
+/// Built-in themes followed by themes from the file, a theme in the file replaces the built-in one with the same name.
+/// Missing file is not an error, there are just no custom themes
+pub fn load_all_themes(path: &Path) -> Result<Vec<Theme>, String> {
+    let mut themes = Theme::built_in();
+    if !path.exists() {
+        return Ok(themes);
+    }
+    for theme in load_themes(path)? {
+        match themes.iter_mut().find(|existing| existing.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => themes.push(theme),
+        }
+    }
+    Ok(themes)
+}
+
+/// Themes file looks like this, colours are sRGB hex:
+/// ```text
+/// [My theme]
+/// background = #000000
+/// 0 = #101010
+/// 1 = #ffffff
+/// ```
+/// States have to go from 0 without gaps
+fn load_themes(path: &Path) -> Result<Vec<Theme>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut themes: Vec<Theme> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            themes.push(Theme {
+                name: name.trim().to_string(),
+                colors: Vec::new(),
+                background: [0.0; 3],
+            });
+            continue;
+        }
+        let theme = themes.last_mut().ok_or_else(|| error("expected [theme name] first"))?;
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected key = #rrggbb"))?;
+        let color = parse_hex(value.trim()).ok_or_else(|| error("expected colour like #rrggbb"))?;
+        match key.trim() {
+            "background" => theme.background = color,
+            state => match state.parse::<usize>() {
+                Ok(state) if state == theme.colors.len() && state < MAX_STATES => theme.colors.push(color),
+                Ok(_) => return Err(error(&format!("expected state {}", theme.colors.len()))),
+                Err(_) => return Err(error(&format!("unknown key \"{}\"", state))),
+            },
+        }
+    }
+    if let Some(theme) = themes.iter().find(|theme| theme.colors.len() < 2) {
+        return Err(format!("theme \"{}\" needs colours for states 0 and 1", theme.name));
+    }
+    Ok(themes)
+}
+
+pub fn save_themes(path: &Path, themes: &[Theme]) -> Result<(), String> {
+    let mut text = String::from("# Colours are sRGB hex, state N is the colour of cells with value N\n");
+    for theme in themes {
+        text += &format!("\n[{}]\nbackground = {}\n", theme.name, to_hex(theme.background));
+        for (state, color) in theme.colors.iter().enumerate() {
+            text += &format!("{} = {}\n", state, to_hex(*color));
+        }
+    }
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
+
+fn parse_hex(value: &str) -> Option<[f32; 3]> {
+    let hex = value.strip_prefix('#')?;
+    (hex.len() == 6).then_some(())?;
+    u32::from_str_radix(hex, 16).ok().map(hex_to_linear)
+}
+
+fn to_hex(color: [f32; 3]) -> String {
+    let [r, g, b] = linear_to_srgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn hex_to_linear(color: u32) -> [f32; 3] {
+    [color >> 16, color >> 8, color].map(|channel| srgb_to_linear((channel & 0xff) as u8))
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(color: [f32; 3]) -> [u8; 3] {
+    color.map(|c| {
+        let srgb = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (srgb.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
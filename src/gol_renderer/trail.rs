@@ -0,0 +1,263 @@
+use super::GoLRenderer;
+use crate::gol::GoL;
+use egui_wgpu::wgpu;
+
+/// Half precision is filterable, fade steps have to stay above its resolution near 1, see MIN_TRAIL_DECAY
+const TRAIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+pub const MIN_TRAIL_DECAY: f32 = 0.005;
+
+pub struct TrailSettings {
+    pub is_enabled: bool,
+    /// Share of the brightness lost every generation
+    pub decay: f32,
+    /// Linear, the trail is blended from the dead colour to this one
+    pub color: [f32; 3],
+}
+
+impl TrailSettings {
+    pub fn new() -> Self {
+        Self {
+            is_enabled: false,
+            decay: 0.05,
+            color: [0.8, 0.15, 0.03],
+        }
+    }
+
+    pub(super) fn get_uniform(&self) -> TrailUniform {
+        let [r, g, b] = self.color;
+        TrailUniform {
+            color: [r, g, b, self.is_enabled as u32 as f32],
+        }
+    }
+}
+
+/// Part of the grid shader uniform
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct TrailUniform {
+    /// w is 1 when the trail is drawn
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TrailUpdateUniform {
+    /// x is the decay, rest is padding
+    decay: [f32; 4],
+}
+
+/// How recently every cell was alive, 1 for alive cells and fading every generation after. Two textures take turns,
+/// the new one is rendered from the old one and the current cells
+pub struct Trail {
+    update_bind_group_layout: wgpu::BindGroupLayout,
+    update_pipeline: wgpu::RenderPipeline,
+    update_uniform_buffer: wgpu::Buffer,
+    /// Texture and sampler for the grid shader, see get_bind_group()
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Created at the grid size on first use, dropped to start over
+    textures: Option<[wgpu::Texture; 2]>,
+    /// Index of the texture with the newest trail
+    current: usize,
+    is_enabled: bool,
+    decay: f32,
+}
+
+impl Trail {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding: u32, sample_type: wgpu::TextureSampleType| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let update_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Uint),
+                texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        min_binding_size: None,
+                        has_dynamic_offset: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let update_pipeline = GoLRenderer::create_pipeline(
+            device,
+            TRAIL_FORMAT,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&update_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("trail_update.wgsl")),
+            wgpu::BlendState::REPLACE,
+            None,
+            &[],
+        );
+        let update_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<TrailUpdateUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: true }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            update_bind_group_layout,
+            update_pipeline,
+            update_uniform_buffer,
+            bind_group_layout,
+            sampler,
+            textures: None,
+            current: 0,
+            is_enabled: false,
+            decay: TrailSettings::new().decay,
+        }
+    }
+
+    /// Layout of get_bind_group(), texture at binding 0 and sampler at binding 1
+    pub fn get_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Turning the trail on starts it from scratch
+    pub fn set_settings(&mut self, settings: &TrailSettings) {
+        if settings.is_enabled && !self.is_enabled {
+            self.textures = None;
+        }
+        self.is_enabled = settings.is_enabled;
+        self.decay = settings.decay.clamp(MIN_TRAIL_DECAY, 1.0);
+    }
+
+    /// Fades the trail and adds the current alive cells, has to be called after every GoL::update()
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gol: &GoL) {
+        if !self.is_enabled {
+            return;
+        }
+        let uniform = TrailUpdateUniform {
+            decay: [self.decay, 0.0, 0.0, 0.0],
+        };
+        queue.write_buffer(&self.update_uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        self.create_textures(device, gol);
+        let textures = self.textures.as_ref().unwrap();
+        let previous = textures[self.current].create_view(&wgpu::TextureViewDescriptor::default());
+        let target = textures[1 - self.current].create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(gol.get_current_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&previous),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.update_uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: None,
+            layout: &self.update_bind_group_layout,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Trail Encoder") });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Trail Render Pass"),
+                timestamp_writes: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    resolve_target: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.update_pipeline);
+            render_pass.set_bind_group(0, Some(&bind_group), &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+        self.current = 1 - self.current;
+    }
+
+    /// Newest trail, empty until the first update()
+    pub fn get_bind_group(&mut self, device: &wgpu::Device, gol: &GoL) -> wgpu::BindGroup {
+        self.create_textures(device, gol);
+        let view = self.textures.as_ref().unwrap()[self.current].create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: None,
+            layout: &self.bind_group_layout,
+        })
+    }
+
+    /// Recreated (so cleared) when missing or the grid size changes
+    fn create_textures(&mut self, device: &wgpu::Device, gol: &GoL) {
+        let (width, height) = gol.get_size();
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        if self.textures.as_ref().is_none_or(|textures| textures[0].size() != size) {
+            self.textures = Some([0, 1].map(|_| {
+                device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Trail"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: TRAIL_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+            }));
+            self.current = 0;
+        }
+    }
+}
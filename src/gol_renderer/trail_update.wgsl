@@ -0,0 +1,45 @@
+struct TrailUpdateUniform {
+    // x is the share of brightness lost per generation
+    decay: vec4<f32>,
+}
+@group(0) @binding(0)
+var cells: texture_2d<u32>;
+@group(0) @binding(1)
+var previous: texture_2d<f32>;
+@group(0) @binding(2)
+var<uniform> trail: TrailUpdateUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const full_quad: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(full_quad[vertex_index], 0.0, 1.0);
+    return out;
+}
+
+// below this the trail is invisible, half floats would also stop decaying at some point
+const cutoff: f32 = 0.002;
+
+// same size as the grid, one texel per cell
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = vec2<i32>(input.clip_position.xy);
+    let alive = f32(textureLoad(cells, cell, 0).x != 0u);
+    var faded = textureLoad(previous, cell, 0).x * (1.0 - trail.decay.x);
+    if (faded < cutoff) {
+        faded = 0.0;
+    }
+    return vec4<f32>(max(alive, faded), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,162 @@
+use crate::cli::CliOptions;
+use crate::gol::GoL;
+use crate::gol_renderer::{get_whole_grid_transforms, load_all_themes, GoLRenderer, DEFAULT_THEMES_PATH};
+use crate::pattern_io::{self, Binarization, ImageImportOptions};
+use crate::recorder::{Recorder, RecordingSettings};
+use crate::screenshot;
+use crate::session::{Session, CONWAY_RULE};
+use egui_wgpu::wgpu;
+use glam::Vec2;
+use std::path::Path;
+
+/// Runs the simulation without a window, e.g. to record animations on a machine without display
+pub fn run(options: &CliOptions) -> Result<(), String> {
+    let (device, queue) = pollster::block_on(create_device())?;
+    let session = options
+        .session
+        .as_ref()
+        .map(|path| {
+            Session::load(path, device.limits().max_texture_dimension_2d)
+                .map_err(|err| format!("can't load {}: {}", path.display(), err))
+        })
+        .transpose()?;
+    if let Some(session) = &session {
+        if session.rule != CONWAY_RULE {
+            return Err(format!("rule {} is not supported", session.rule));
+        }
+    }
+    let (width, height) = session
+        .as_ref()
+        .map_or(options.size, |session| (session.grid_width, session.grid_height));
+    let mut gol = GoL::new(&device, width, height);
+    if let Some(session) = &session {
+        gol.write_area(&queue, &session.cells, 0, 0, width, height);
+        gol.set_generation(session.generation);
+    }
+
+    if let Some(path) = &options.pattern {
+        let image_options = ImageImportOptions {
+            max_width: width,
+            max_height: height,
+            upscale: true,
+            binarization: Binarization::Threshold,
+            threshold: 0.5,
+            invert: false,
+        };
+        let pattern = pattern_io::load_pattern(path, &image_options, (width, height))
+            .map_err(|err| format!("can't load {}: {}", path.display(), err))?;
+        gol.write_area_clipped(
+            &queue,
+            &pattern.data,
+            (width as i64 - pattern.width as i64) / 2,
+            (height as i64 - pattern.height as i64) / 2,
+            pattern.width,
+            pattern.height,
+        );
+    }
+
+    let theme = load_all_themes(Path::new(DEFAULT_THEMES_PATH))
+        .map_err(|err| format!("can't load themes: {}", err))?
+        .into_iter()
+        .find(|theme| theme.name.eq_ignore_ascii_case(&options.theme))
+        .ok_or_else(|| format!("unknown theme \"{}\"", options.theme))?;
+    let mut recorder = options
+        .record
+        .as_ref()
+        .map(|record| {
+            let settings = RecordingSettings {
+                path: record.path.clone(),
+                format: record.format,
+                region: record.region.unwrap_or((0, 0, width, height)),
+                every_generations: record.every_generations,
+                scale: record.scale,
+                frame_delay_ms: record.frame_delay_ms,
+                colors: record.palette.colors(&theme),
+                drop_frames_when_busy: false,
+            };
+            settings.check_region((width, height)).map_err(|err| format!("invalid --region: {}", err))?;
+            Ok::<_, String>(Recorder::start(settings))
+        })
+        .transpose()?;
+    if let Some(recorder) = &mut recorder {
+        // initial state is a frame too
+        recorder.on_generation(&gol, &device, &queue);
+    }
+
+    for _ in 0..options.generations {
+        gol.update(&device, &queue);
+        if let Some(recorder) = &mut recorder {
+            recorder.on_generation(&gol, &device, &queue);
+            recorder.poll(&device);
+        }
+    }
+    log::info!("Simulated {} generations", options.generations);
+
+    if let Some(path) = &options.save_session {
+        // GUI state is kept from the loaded session, so it opens the same way in the window
+        let session = Session {
+            grid_width: width,
+            grid_height: height,
+            cells: pollster::block_on(gol.read_area(&device, &queue, 0, 0, width, height)),
+            generation: gol.get_generation(),
+            rule: CONWAY_RULE.to_string(),
+            is_paused: session.as_ref().is_some_and(|session| session.is_paused),
+            target_tps: session.as_ref().map_or(60, |session| session.target_tps),
+            camera_position: session.as_ref().map_or(Vec2::ZERO, |session| session.camera_position),
+            camera_zoom: session.as_ref().map_or(1.0, |session| session.camera_zoom),
+            camera_rotation: session.as_ref().map_or(0.0, |session| session.camera_rotation),
+        };
+        session
+            .save(path)
+            .map_err(|err| format!("can't save {}: {}", path.display(), err))?;
+        log::info!("Session saved to {}", path.display());
+    }
+
+    if let Some(path) = &options.screenshot {
+        let size = options.screenshot_size;
+        // sRGB target like a typical window surface, so the image looks the same as on screen
+        let mut renderer = GoLRenderer::new(&device, wgpu::TextureFormat::Rgba8UnormSrgb);
+        renderer.set_theme(&queue, &theme);
+        let (view_proj, quad_transform) = get_whole_grid_transforms((width, height), size);
+        let pixels = pollster::block_on(renderer.render_image(&device, &queue, &gol, view_proj, quad_transform, size));
+        screenshot::save_png(path, size.0, size.1, &pixels)
+            .map_err(|err| format!("can't save {}: {}", path.display(), err))?;
+        log::info!("Screenshot {}x{} saved to {}", size.0, size.1, path.display());
+    }
+
+    if let Some(mut recorder) = recorder {
+        recorder.stop(&device);
+        let status = recorder.get_status();
+        let frames = recorder.take_result()?;
+        log::info!("Recorded {} frames ({})", frames, status);
+    }
+    Ok(())
+}
+
+async fn create_device() -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or("no suitable GPU adapter found")?;
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                memory_hints: Default::default(),
+                // headless runs on anything including software adapters, so just take what adapter has
+                required_limits: adapter.limits(),
+            },
+            None,
+        )
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,331 @@
+use crate::gol::{GoL, PackedCells, PendingRead};
+use egui_wgpu::wgpu;
+use std::collections::VecDeque;
+
+/// Cells of all commands (before and after) are kept under this, oldest commands are forgotten first
+const MAX_HISTORY_BYTES: usize = 64 * 1024 * 1024;
+/// Grid snapshots live on the GPU and are as big as the grid, so only the newest few commands keep them
+const MAX_SNAPSHOTS: usize = 8;
+/// GPU memory of all snapshots together, big grids get fewer snapshots (or none) than MAX_SNAPSHOTS
+const MAX_SNAPSHOT_BYTES: usize = 256 * 1024 * 1024;
+
+/// Area of the grid before and after the edit
+struct Patch {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    before: PackedCells,
+    after: PackedCells,
+}
+
+impl Patch {
+    fn write(&self, gol: &GoL, queue: &wgpu::Queue, packed: &PackedCells) {
+        let cells = packed.unpack(self.width as usize * self.height as usize);
+        gol.write_area(queue, &cells, self.x, self.y, self.width, self.height);
+    }
+
+    fn get_bytes(&self) -> usize {
+        self.before.get_bytes() + self.after.get_bytes()
+    }
+}
+
+/// Painted area whose cells before the edit are still being read back, see History::paint_cells()
+struct PendingPatch {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    read: PendingRead,
+    /// Relative to x, y
+    cells: Vec<(u32, u32)>,
+    value: u8,
+}
+
+/// One user action, e.g. a whole brush stroke or a paste
+struct Command {
+    /// Edits don't advance the simulation, so all patches of a command are made in the same generation
+    generation: u64,
+    patches: Vec<Patch>,
+    /// Become patches after the readback, they always come after `patches`
+    pending_patches: Vec<PendingPatch>,
+    /// Whole grid right after the command, taken when the simulation moves past its generation. Lets undo rewind the simulation
+    snapshot: Option<wgpu::Texture>,
+}
+
+impl Command {
+    fn get_bytes(&self) -> usize {
+        self.patches.iter().map(Patch::get_bytes).sum()
+    }
+}
+
+/// Undo/redo of grid edits. Every edit has to go through edit_area(), which remembers the area before and after the change.
+///
+/// When the simulation ran after the edit, undo rewinds the grid to the generation of the edit (using the snapshot)
+/// and then reverts the edit. Commands too old to have a snapshot can't be undone correctly, so they are forgotten.
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    bytes: usize,
+    /// Between begin_command() and end_command() all edits are merged into one command
+    is_grouping: bool,
+    /// Top of undo_stack accepts more patches of the current group
+    is_top_open: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            bytes: 0,
+            is_grouping: false,
+            is_top_open: false,
+        }
+    }
+
+    /// Forget everything, e.g. when the grid is replaced by a loaded session. Pending readbacks are abandoned
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn get_undo_count(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn get_redo_count(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Edits until end_command() are undone together
+    pub fn begin_command(&mut self) {
+        self.is_grouping = true;
+        self.is_top_open = false;
+    }
+
+    /// Waits for the readbacks of cells painted during the command, see paint_cells()
+    pub fn end_command(&mut self, device: &wgpu::Device) {
+        self.is_grouping = false;
+        self.is_top_open = false;
+        self.finish_pending_patches(device);
+    }
+
+    /// Reads the area, lets edit change the cells and writes them back with a single write_area()
+    #[allow(clippy::too_many_arguments)]
+    pub fn edit_area(
+        &mut self,
+        gol: &GoL,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        edit: impl FnOnce(&mut [u8]),
+    ) {
+        // keeps patches in the order they were made
+        self.finish_pending_patches(device);
+        let before = pollster::block_on(gol.read_area(device, queue, x, y, width, height));
+        let mut after = before.clone();
+        edit(&mut after);
+        if after == before {
+            return;
+        }
+        gol.write_area(queue, &after, x, y, width, height);
+
+        let patch = Patch {
+            x,
+            y,
+            width,
+            height,
+            before: PackedCells::pack(&before),
+            after: PackedCells::pack(&after),
+        };
+        self.bytes += patch.get_bytes();
+        self.get_open_command(gol).patches.push(patch);
+        self.enforce_memory_limit();
+    }
+
+    /// Sets the cells to value without waiting for the GPU, for edits made every frame like brush strokes.
+    /// Cells have to be inside the grid. They are written as runs of cells in a row, so the area doesn't need to be read first.
+    /// Their bounding box is read back in the background and becomes a patch at end_command()
+    pub fn paint_cells(&mut self, gol: &GoL, device: &wgpu::Device, queue: &wgpu::Queue, cells: &[(u32, u32)], value: u8) {
+        let Some(x) = cells.iter().map(|(x, _)| *x).min() else {
+            return;
+        };
+        let y = cells.iter().map(|(_, y)| *y).min().unwrap();
+        let width = cells.iter().map(|(x, _)| *x).max().unwrap() - x + 1;
+        let height = cells.iter().map(|(_, y)| *y).max().unwrap() - y + 1;
+        // submitted before the writes below, so it sees the cells before the edit
+        let read = gol.start_area_read(device, queue, x, y, width, height);
+
+        let mut sorted: Vec<(u32, u32)> = cells.iter().map(|(cell_x, cell_y)| (*cell_y, *cell_x)).collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+        for run in sorted.chunk_by(|a, b| a.0 == b.0 && a.1 + 1 == b.1) {
+            let (row, column) = run[0];
+            gol.write_area(queue, &vec![value; run.len()], column, row, run.len() as u32, 1);
+        }
+
+        let pending_patch = PendingPatch {
+            x,
+            y,
+            width,
+            height,
+            read,
+            cells: sorted.into_iter().map(|(row, column)| (column - x, row - y)).collect(),
+            value,
+        };
+        self.get_open_command(gol).pending_patches.push(pending_patch);
+    }
+
+    /// Command new edits are added to, a new one unless a group is open
+    fn get_open_command(&mut self, gol: &GoL) -> &mut Command {
+        self.redo_stack.clear();
+        if !self.is_top_open || self.undo_stack.is_empty() {
+            self.undo_stack.push_back(Command {
+                generation: gol.get_generation(),
+                patches: Vec::new(),
+                pending_patches: Vec::new(),
+                snapshot: None,
+            });
+            self.is_top_open = self.is_grouping;
+        }
+        self.undo_stack.back_mut().unwrap()
+    }
+
+    /// Turns finished readbacks of paint_cells() into patches, blocks until all of them are done
+    fn finish_pending_patches(&mut self, device: &wgpu::Device) {
+        if self.undo_stack.iter().all(|command| command.pending_patches.is_empty()) {
+            return;
+        }
+        device.poll(wgpu::Maintain::Wait);
+        for command in &mut self.undo_stack {
+            for pending in command.pending_patches.drain(..) {
+                // failed read (e.g. lost device) can't be undone, it's dropped
+                let Some(Ok(before)) = pending.read.try_take() else {
+                    continue;
+                };
+                let mut after = before.clone();
+                for (x, y) in &pending.cells {
+                    after[(y * pending.width + x) as usize] = pending.value;
+                }
+                if after == before {
+                    continue;
+                }
+                let patch = Patch {
+                    x: pending.x,
+                    y: pending.y,
+                    width: pending.width,
+                    height: pending.height,
+                    before: PackedCells::pack(&before),
+                    after: PackedCells::pack(&after),
+                };
+                self.bytes += patch.get_bytes();
+                command.patches.push(patch);
+            }
+        }
+        self.enforce_memory_limit();
+    }
+
+    /// Same as GoL::write_area_clipped(), but undoable
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_area_clipped(
+        &mut self,
+        gol: &GoL,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        x: i64,
+        y: i64,
+        width: u32,
+        height: u32,
+    ) {
+        let Some((area_x, area_y, area_width, area_height)) = gol.clip_area(x, y, width, height) else {
+            return;
+        };
+        self.edit_area(gol, device, queue, area_x, area_y, area_width, area_height, |cells| {
+            for row in 0..area_height as usize {
+                let data_start = (area_y as i64 + row as i64 - y) as usize * width as usize + (area_x as i64 - x) as usize;
+                cells[row * area_width as usize..(row + 1) * area_width as usize]
+                    .copy_from_slice(&data[data_start..data_start + area_width as usize]);
+            }
+        });
+    }
+
+    /// Has to be called before every GoL::update(). Snapshots the grid if the last command was made in the current generation
+    pub fn before_generation_advance(&mut self, gol: &GoL, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // stepping the simulation is a change too, what was undone can't be redone on top of it
+        self.redo_stack.clear();
+        // a stroke drawn while the simulation runs is split into one command per generation
+        self.is_top_open = false;
+        let Some(command) = self.undo_stack.back_mut() else {
+            return;
+        };
+        if command.generation != gol.get_generation() || command.snapshot.is_some() {
+            return;
+        }
+        let (width, height) = gol.get_size();
+        let snapshot_count = MAX_SNAPSHOTS.min(MAX_SNAPSHOT_BYTES / (width as usize * height as usize));
+        if snapshot_count == 0 {
+            return;
+        }
+        command.snapshot = Some(gol.create_snapshot(device, queue));
+        for command in self.undo_stack.iter_mut().rev().filter(|command| command.snapshot.is_some()).skip(snapshot_count) {
+            command.snapshot = None;
+        }
+    }
+
+    /// Returns a message describing what happened, None if there is nothing to undo
+    pub fn undo(&mut self, gol: &mut GoL, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<String> {
+        self.finish_pending_patches(device);
+        let command = self.undo_stack.pop_back()?;
+        self.is_top_open = false;
+        let mut message = "Undone".to_string();
+        if gol.get_generation() != command.generation {
+            let Some(snapshot) = &command.snapshot else {
+                // writing old cells over the evolved grid would be wrong, and all older commands are even older
+                self.undo_stack.clear();
+                self.bytes = 0;
+                return Some("Can't undo, the edit is too old to rewind the simulation".to_string());
+            };
+            gol.restore_snapshot(device, queue, snapshot);
+            gol.set_generation(command.generation);
+            message = format!("Undone and rewound to generation {}", command.generation);
+        }
+        for patch in command.patches.iter().rev() {
+            patch.write(gol, queue, &patch.before);
+        }
+        self.bytes -= command.get_bytes();
+        self.redo_stack.push(Command {
+            snapshot: None,
+            ..command
+        });
+        Some(message)
+    }
+
+    pub fn redo(&mut self, gol: &GoL, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<String> {
+        self.finish_pending_patches(device);
+        let command = self.redo_stack.pop()?;
+        for patch in &command.patches {
+            patch.write(gol, queue, &patch.after);
+        }
+        self.bytes += command.get_bytes();
+        self.undo_stack.push_back(command);
+        self.is_top_open = false;
+        Some("Redone".to_string())
+    }
+
+    fn enforce_memory_limit(&mut self) {
+        // redo stack is not counted, it's cleared by every new edit
+        while self.bytes > MAX_HISTORY_BYTES {
+            let Some(command) = self.undo_stack.pop_front() else {
+                break;
+            };
+            self.bytes -= command.get_bytes();
+            if self.undo_stack.is_empty() {
+                self.is_top_open = false;
+            }
+        }
+    }
+}
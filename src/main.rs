@@ -1,24 +1,28 @@
+mod cli;
+mod clipboard;
 mod drawing;
 mod gol;
 mod gol_manager;
 mod gol_renderer;
+mod headless;
+mod history;
+mod pattern_io;
+mod pattern_library;
 mod perf_monitor;
+mod recorder;
+mod screenshot;
+mod session;
+mod shader_reload;
+mod tracker;
 
-use crate::gol::GoL;
-use crate::gol_renderer::GoLRenderer;
-use crate::perf_monitor::PerfMonitor;
-use drawing::GoLDrawing;
-use egui::Align2;
+use cli::CliOptions;
 use egui_wgpu::wgpu;
-use glam::{vec2, Mat3, Mat4, UVec2, Vec2};
 use gol_manager::GoLManager;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::task::Context;
-use std::time::Duration;
-use wgpu::util::DeviceExt;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::ActiveEventLoop;
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::WindowId;
 
@@ -107,20 +111,18 @@ impl State {
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width <= 0 || new_size.height <= 0 {
+        if new_size.width == 0 || new_size.height == 0 {
             return;
         }
         self.size = new_size;
         self.surface_config.width = new_size.width;
         self.surface_config.height = new_size.height;
         self.surface.configure(&self.device, &self.surface_config);
-        self.gol_manager
-            .handle_aspect_ratio_change(new_size.width as f32 / new_size.height as f32);
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
         self.gol_manager
-            .handle_input(event, self.window.clone(), &self.queue)
+            .handle_input(event, self.window.clone(), &self.device, &self.queue)
     }
 
     pub fn update(&mut self) {
@@ -155,6 +157,8 @@ impl State {
 pub struct App {
     state: Option<State>,
     window: Option<Arc<winit::window::Window>>,
+    /// Session restored when the window is created, from --session argument
+    startup_session: Option<PathBuf>,
 }
 
 impl ApplicationHandler for App {
@@ -166,7 +170,12 @@ impl ApplicationHandler for App {
         let window_handle = Arc::new(window);
         self.window = Some(window_handle.clone());
         if is_first_window_handle {
-            let state = pollster::block_on(State::new(window_handle.clone()));
+            let mut state = pollster::block_on(State::new(window_handle.clone()));
+            if let Some(path) = self.startup_session.take() {
+                if let Err(err) = state.gol_manager.load_session(&state.device, &state.queue, &path) {
+                    log::error!("Can't load session {}: {}", path.display(), err);
+                }
+            }
             self.state = Some(state);
         }
     }
@@ -229,8 +238,31 @@ impl ApplicationHandler for App {
 }
 
 fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if options.headless {
+        if let Err(err) = headless::run(&options) {
+            log::error!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = winit::event_loop::EventLoop::builder().build().unwrap();
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
-    let mut app = App::default();
+    let mut app = App {
+        startup_session: options.session,
+        ..Default::default()
+    };
     event_loop.run_app(&mut app).unwrap();
 }
@@ -0,0 +1,184 @@
+use super::{PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+use crate::gol_renderer::Theme;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// Checked before decoding, so a small file declaring huge dimensions can't allocate gigabytes. 8192x8192 pixels
+const MAX_IMAGE_PIXELS: u64 = 1 << 26;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Binarization {
+    /// Pixels brighter than threshold become alive
+    Threshold,
+    /// Floyd-Steinberg error diffusion, keeps gradients of photos visible
+    Dither,
+}
+
+pub struct ImageImportOptions {
+    /// Image is scaled to fit into this rectangle, aspect ratio is kept
+    pub max_width: u32,
+    pub max_height: u32,
+    /// When false, images smaller than the rectangle keep one cell per pixel
+    pub upscale: bool,
+    pub binarization: Binarization,
+    /// 0 to 1, only used with Binarization::Threshold
+    pub threshold: f32,
+    /// By default bright pixels are alive (same as on the screen), invert for dark logos on white background
+    pub invert: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImagePalette {
+    /// Black dead and white alive cells
+    Monochrome,
+    /// Colours of the current theme, same as GoLRenderer draws
+    Renderer,
+}
+
+impl ImagePalette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImagePalette::Monochrome => "Black and white",
+            ImagePalette::Renderer => "Screen colors",
+        }
+    }
+
+    /// sRGB colour of every state, at least dead and alive
+    pub fn colors(&self, theme: &Theme) -> Vec<[u8; 3]> {
+        match self {
+            ImagePalette::Monochrome => vec![[0, 0, 0], [255, 255, 255]],
+            ImagePalette::Renderer => theme.get_srgb_colors(),
+        }
+    }
+}
+
+pub fn is_png(path: &Path) -> bool {
+    let mut signature = [0u8; 8];
+    File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut signature))
+        .is_ok_and(|_| signature == PNG_SIGNATURE)
+}
+
+pub fn load_png(path: &Path, options: &ImageImportOptions) -> Result<Pattern, PatternError> {
+    let (luminance, width, height) = read_luminance(path)?;
+    let (luminance, width, height) = resample_to_fit(&luminance, width, height, options);
+    let luminance: Vec<f32> = if options.invert {
+        luminance.iter().map(|l| 1.0 - l).collect()
+    } else {
+        luminance
+    };
+    let data = match options.binarization {
+        Binarization::Threshold => luminance.iter().map(|l| (*l > options.threshold) as u8).collect(),
+        Binarization::Dither => dither(luminance, width, height),
+    };
+    Ok(Pattern::new(data, width, height))
+}
+
+/// One pixel per cell. Written as indexed image, so cell values are used as palette indices directly,
+/// values past the end of the palette get its last colour
+pub fn save_png(path: &Path, pattern: &Pattern, colors: &[[u8; 3]]) -> Result<(), PatternError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, pattern.width, pattern.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(colors.concat());
+    let mut writer = encoder.write_header().map_err(image_error)?;
+    let last_index = (colors.len() - 1) as u8;
+    let data: Vec<u8> = pattern.data.iter().map(|v| (*v).min(last_index)).collect();
+    writer.write_image_data(&data).map_err(image_error)?;
+    writer.finish().map_err(image_error)
+}
+
+fn image_error(err: impl std::fmt::Display) -> PatternError {
+    PatternError::Image(err.to_string())
+}
+
+/// Returns luminance (0 to 1) of every pixel. Transparent pixels are blended over black, so they become dead
+fn read_luminance(path: &Path) -> Result<(Vec<f32>, u32, u32), PatternError> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(image_error)?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    if width as i64 > MAX_PATTERN_SIDE * 16
+        || height as i64 > MAX_PATTERN_SIDE * 16
+        || width as u64 * height as u64 > MAX_IMAGE_PIXELS
+    {
+        return Err(PatternError::Image(format!("image is too big ({}x{})", width, height)));
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(image_error)?;
+    let channels = info.color_type.samples();
+    let luminance = buffer[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| {
+            let (rgb, alpha) = match pixel {
+                [l] => ([*l; 3], 255),
+                [l, a] => ([*l; 3], *a),
+                [r, g, b] => ([*r, *g, *b], 255),
+                [r, g, b, a] => ([*r, *g, *b], *a),
+                _ => unreachable!("png has at most 4 channels"),
+            };
+            let luminance = 0.2126 * rgb[0] as f32 + 0.7152 * rgb[1] as f32 + 0.0722 * rgb[2] as f32;
+            luminance / 255.0 * (alpha as f32 / 255.0)
+        })
+        .collect();
+    Ok((luminance, info.width, info.height))
+}
+
+/// Area averaging when image is scaled down, nearest neighbour when it's scaled up (keeps pixel art sharp)
+fn resample_to_fit(
+    luminance: &[f32],
+    width: u32,
+    height: u32,
+    options: &ImageImportOptions,
+) -> (Vec<f32>, u32, u32) {
+    let (max_width, max_height) = (options.max_width, options.max_height);
+    let scale = (max_width as f32 / width as f32).min(max_height as f32 / height as f32);
+    let scale = if options.upscale { scale } else { scale.min(1.0) };
+    let out_width = ((width as f32 * scale).round() as u32).clamp(1, max_width.max(1));
+    let out_height = ((height as f32 * scale).round() as u32).clamp(1, max_height.max(1));
+    let mut out = Vec::with_capacity((out_width * out_height) as usize);
+    for out_y in 0..out_height {
+        let y0 = (out_y as u64 * height as u64 / out_height as u64) as u32;
+        let y1 = (((out_y + 1) as u64 * height as u64).div_ceil(out_height as u64) as u32).max(y0 + 1);
+        for out_x in 0..out_width {
+            let x0 = (out_x as u64 * width as u64 / out_width as u64) as u32;
+            let x1 = (((out_x + 1) as u64 * width as u64).div_ceil(out_width as u64) as u32).max(x0 + 1);
+            let mut sum = 0.0;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    sum += luminance[(y * width + x) as usize];
+                }
+            }
+            out.push(sum / ((x1 - x0) * (y1 - y0)) as f32);
+        }
+    }
+    (out, out_width, out_height)
+}
+
+fn dither(mut luminance: Vec<f32>, width: u32, height: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let mut data = vec![0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let alive = luminance[index] > 0.5;
+            data[index] = alive as u8;
+            let error = luminance[index] - if alive { 1.0 } else { 0.0 };
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    luminance[(y + dy) * width + nx as usize] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    data
+}
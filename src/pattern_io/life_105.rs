@@ -0,0 +1,121 @@
+use super::{pattern_from_cells, split_name_comment, PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+
+// https://conwaylife.com/wiki/Life_1.05
+// Pattern is split into blocks, each block starts with "#P x y" (position of its top left cell relative to the center)
+// and is followed by rows of "." (dead) and "*" (alive). "#D" lines are descriptions, "#N" and "#R" specify rules
+
+pub const HEADER: &str = "#Life 1.05";
+
+pub fn parse(text: &str, max_size: (u32, u32)) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim_end() == HEADER => {}
+        _ => return Err(PatternError::parse(1, format!("expected \"{}\" header", HEADER))),
+    }
+
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut cells = Vec::new();
+    let mut block_origin = (0i64, 0i64);
+    let mut block_row = 0i64;
+
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let line = line.trim_end();
+        if let Some(directive) = line.strip_prefix('#') {
+            let kind = directive.chars().next().unwrap_or(' ');
+            let argument = &directive[kind.len_utf8().min(directive.len())..];
+            let argument = argument.strip_prefix(' ').unwrap_or(argument);
+            match kind {
+                'D' => match split_name_comment(argument) {
+                    Some(pattern_name) => name = Some(pattern_name.to_string()),
+                    None => comments.push(argument.to_string()),
+                },
+                'N' => {}
+                'R' => check_rule(argument, line_number)?,
+                'P' => {
+                    block_origin = parse_position(argument, line_number)?;
+                    block_row = 0;
+                }
+                _ => {
+                    return Err(PatternError::parse(
+                        line_number,
+                        format!("unknown directive \"#{}\"", directive),
+                    ))
+                }
+            }
+            continue;
+        }
+
+        for (column, char) in line.chars().enumerate() {
+            match char {
+                '.' => {}
+                '*' => cells.push((block_origin.0 + column as i64, block_origin.1 + block_row)),
+                _ => {
+                    return Err(PatternError::parse(
+                        line_number,
+                        format!("unexpected character '{}' at column {}, expected '.' or '*'", char, column + 1),
+                    ))
+                }
+            }
+        }
+        block_row += 1;
+    }
+
+    pattern_from_cells(&cells, name, comments, max_size)
+}
+
+/// Only Conway's rules are simulated. Rule is written as survival/birth, e.g. "23/3"
+fn check_rule(rule: &str, line_number: usize) -> Result<(), PatternError> {
+    let normalized: String = rule.chars().filter(|c| !c.is_whitespace()).collect();
+    if normalized == "23/3" {
+        Ok(())
+    } else {
+        Err(PatternError::parse(
+            line_number,
+            format!("unsupported rule \"{}\", only 23/3 is supported", rule),
+        ))
+    }
+}
+
+fn parse_position(argument: &str, line_number: usize) -> Result<(i64, i64), PatternError> {
+    let coordinates: Vec<&str> = argument.split_whitespace().collect();
+    let [x, y] = coordinates.as_slice() else {
+        return Err(PatternError::parse(
+            line_number,
+            "expected two coordinates after #P",
+        ));
+    };
+    let parse_coordinate = |value: &str| {
+        value
+            .parse::<i64>()
+            .ok()
+            .filter(|v| v.abs() <= MAX_PATTERN_SIDE)
+            .ok_or_else(|| PatternError::parse(line_number, format!("invalid coordinate \"{}\"", value)))
+    };
+    Ok((parse_coordinate(x)?, parse_coordinate(y)?))
+}
+
+/// Whole pattern is written as single block centered around 0,0. Trailing dead cells in rows are omitted
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#D Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#D {}\n", comment));
+    }
+    out.push_str("#N\n");
+    out.push_str(&format!(
+        "#P {} {}\n",
+        -(pattern.width as i64 / 2),
+        -(pattern.height as i64 / 2)
+    ));
+    for row in pattern.data.chunks(pattern.width.max(1) as usize) {
+        let row: String = row.iter().map(|v| if *v != 0 { '*' } else { '.' }).collect();
+        out.push_str(row.trim_end_matches('.'));
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,77 @@
+use super::{pattern_from_cells, split_name_comment, PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+
+// https://conwaylife.com/wiki/Life_1.06
+// After the header every line is "x y" coordinate of an alive cell. Format itself has no comments,
+// but "#D" description lines (same as in Life 1.05) are accepted and written to keep metadata
+
+pub const HEADER: &str = "#Life 1.06";
+
+pub fn parse(text: &str, max_size: (u32, u32)) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim_end() == HEADER => {}
+        _ => return Err(PatternError::parse(1, format!("expected \"{}\" header", HEADER))),
+    }
+
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut cells = Vec::new();
+
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let line = line.trim_end();
+        if let Some(argument) = line.strip_prefix("#D") {
+            let argument = argument.strip_prefix(' ').unwrap_or(argument);
+            match split_name_comment(argument) {
+                Some(pattern_name) => name = Some(pattern_name.to_string()),
+                None => comments.push(argument.to_string()),
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            return Err(PatternError::parse(
+                line_number,
+                "only #D lines are allowed after the header",
+            ));
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let coordinates: Vec<&str> = line.split_whitespace().collect();
+        let [x, y] = coordinates.as_slice() else {
+            return Err(PatternError::parse(
+                line_number,
+                format!("expected \"x y\" coordinates, got \"{}\"", line),
+            ));
+        };
+        let parse_coordinate = |value: &str| {
+            value
+                .parse::<i64>()
+                .ok()
+                .filter(|v| v.abs() <= MAX_PATTERN_SIDE)
+                .ok_or_else(|| PatternError::parse(line_number, format!("invalid coordinate \"{}\"", value)))
+        };
+        cells.push((parse_coordinate(x)?, parse_coordinate(y)?));
+    }
+
+    pattern_from_cells(&cells, name, comments, max_size)
+}
+
+/// Coordinates are written relative to the pattern center, same as Life 1.05 blocks
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#D Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#D {}\n", comment));
+    }
+    let offset_x = pattern.width as i64 / 2;
+    let offset_y = pattern.height as i64 / 2;
+    for (x, y) in pattern.alive_cells() {
+        out.push_str(&format!("{} {}\n", x as i64 - offset_x, y as i64 - offset_y));
+    }
+    out
+}
@@ -0,0 +1,500 @@
+use super::{split_name_comment, PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+use std::collections::HashMap;
+
+// https://conwaylife.com/wiki/Macrocell
+// Golly's quadtree format. Every line after the header defines a node, nodes are numbered from 1 in order of appearance, 0 is empty node.
+// Leaf is 8x8 block written as rows of "." and "*", each row ends with "$".
+// Other nodes are "level nw ne sw se", where level is log2 of the node size and children are indices of earlier nodes.
+// Identical subtrees are stored once, so patterns way bigger than the game texture are only a few kilobytes
+
+pub const HEADER: &str = "[M2]";
+
+const LEAF_LEVEL: u32 = 3;
+const LEAF_SIZE: u64 = 1 << LEAF_LEVEL;
+/// Coordinates are stored in u64, so node size 2^63 is the maximum
+const MAX_LEVEL: u32 = 63;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    /// Each byte is a row, lowest bit is the leftmost cell
+    Leaf([u8; 8]),
+    /// Children are nw, ne, sw, se
+    Branch { level: u32, children: [u32; 4] },
+}
+
+/// Rectangle of alive cells in node coordinates, max is inclusive
+#[derive(Clone, Copy)]
+struct Bounds {
+    min_x: u64,
+    min_y: u64,
+    max_x: u64,
+    max_y: u64,
+}
+
+impl Bounds {
+    fn offset(self, x: u64, y: u64) -> Self {
+        Self {
+            min_x: self.min_x + x,
+            min_y: self.min_y + y,
+            max_x: self.max_x + x,
+            max_y: self.max_y + y,
+        }
+    }
+    fn union(self, other: Self) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+/// Sparse pattern, kept as the quadtree it was loaded from. Only requested windows are turned into dense patterns
+pub struct Macrocell {
+    /// Index 0 is a placeholder for the empty node, so file indices can be used directly
+    nodes: Vec<Node>,
+    root: u32,
+    /// Bounding box of alive cells, None when pattern is empty
+    bounds: Option<Bounds>,
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Macrocell {
+    pub fn parse(text: &str) -> Result<Self, PatternError> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.starts_with(HEADER) => {}
+            _ => return Err(PatternError::parse(1, format!("expected \"{}\" header", HEADER))),
+        }
+
+        let mut name = None;
+        let mut comments = Vec::new();
+        let mut nodes = vec![Node::Leaf([0; 8])];
+
+        for (index, line) in lines {
+            let line_number = index + 1;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(directive) = line.strip_prefix('#') {
+                let kind = directive.chars().next().unwrap_or(' ');
+                let argument = &directive[kind.len_utf8().min(directive.len())..];
+                let argument = argument.strip_prefix(' ').unwrap_or(argument);
+                match kind {
+                    'C' | 'D' => match split_name_comment(argument) {
+                        Some(pattern_name) => name = Some(pattern_name.to_string()),
+                        None => comments.push(argument.to_string()),
+                    },
+                    'R' => check_rule(argument, line_number)?,
+                    // generation counter, frame info and other Golly extras are not needed
+                    _ => {}
+                }
+                continue;
+            }
+            let node = if line.starts_with(['.', '*', '$']) {
+                parse_leaf(line, line_number)?
+            } else {
+                parse_branch(line, line_number, &nodes)?
+            };
+            nodes.push(node);
+        }
+
+        if nodes.len() == 1 {
+            return Err(PatternError::parse(0, "macrocell file has no nodes"));
+        }
+        let root = (nodes.len() - 1) as u32;
+        let mut macrocell = Self {
+            nodes,
+            root,
+            bounds: None,
+            name,
+            comments,
+        };
+        macrocell.bounds = macrocell.compute_bounds();
+        Ok(macrocell)
+    }
+
+    /// Builds quadtree from the dense pattern, identical subtrees are shared
+    pub fn from_pattern(pattern: &Pattern) -> Self {
+        let side = pattern.width.max(pattern.height).max(LEAF_SIZE as u32).next_power_of_two();
+        let level = side.trailing_zeros();
+        let mut builder = Builder {
+            pattern,
+            nodes: vec![Node::Leaf([0; 8])],
+            indices: HashMap::new(),
+        };
+        let root = builder.build(level, 0, 0);
+        let mut macrocell = Self {
+            nodes: builder.nodes,
+            root,
+            bounds: None,
+            name: pattern.name.clone(),
+            comments: pattern.comments.clone(),
+        };
+        macrocell.bounds = macrocell.compute_bounds();
+        macrocell
+    }
+
+    pub fn write(&self) -> String {
+        let mut out = format!("{}\n#R B3/S23\n", HEADER);
+        if let Some(name) = &self.name {
+            out.push_str(&format!("#C Name: {}\n", name));
+        }
+        for comment in &self.comments {
+            out.push_str(&format!("#C {}\n", comment));
+        }
+        if self.root == 0 {
+            // file needs at least one node, even if pattern is empty
+            out.push_str("$\n");
+            return out;
+        }
+        for node in &self.nodes[1..] {
+            match node {
+                Node::Leaf(rows) => {
+                    let last_row = rows.iter().rposition(|row| *row != 0).map_or(0, |i| i + 1);
+                    for row in &rows[..last_row] {
+                        let cells: String = (0..8).map(|x| if row >> x & 1 == 1 { '*' } else { '.' }).collect();
+                        out.push_str(cells.trim_end_matches('.'));
+                        out.push('$');
+                    }
+                    if last_row == 0 {
+                        out.push('$');
+                    }
+                }
+                Node::Branch { level, children } => {
+                    out.push_str(&format!(
+                        "{} {} {} {} {}",
+                        level, children[0], children[1], children[2], children[3]
+                    ));
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Size of the alive cells bounding box
+    pub fn content_size(&self) -> (u64, u64) {
+        self.bounds.map_or((0, 0), |bounds| {
+            (bounds.max_x - bounds.min_x + 1, bounds.max_y - bounds.min_y + 1)
+        })
+    }
+
+    pub fn alive_cell_count(&self) -> u64 {
+        let mut memo = HashMap::new();
+        self.population(self.root, &mut memo)
+    }
+
+    /// Cuts out part of the pattern as dense pattern. x and y are relative to the top left corner of the alive cells bounding box
+    pub fn window(&self, x: u64, y: u64, width: u32, height: u32) -> Pattern {
+        let mut pattern = Pattern::new(vec![0; width as usize * height as usize], width, height);
+        pattern.name = self.name.clone();
+        pattern.comments = self.comments.clone();
+        if let Some(bounds) = self.bounds {
+            let window = Window {
+                x: bounds.min_x.saturating_add(x),
+                y: bounds.min_y.saturating_add(y),
+                width: width as u64,
+                height: height as u64,
+            };
+            self.fill_window(self.root, 0, 0, &window, &mut pattern);
+        }
+        pattern
+    }
+
+    fn level(&self, index: u32) -> u32 {
+        match self.nodes[index as usize] {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Branch { level, .. } => level,
+        }
+    }
+
+    fn compute_bounds(&self) -> Option<Bounds> {
+        let mut memo = HashMap::new();
+        self.node_bounds(self.root, &mut memo)
+    }
+
+    /// Memoized per node, so shared subtrees are visited once even if they are repeated millions of times
+    fn node_bounds(&self, index: u32, memo: &mut HashMap<u32, Option<Bounds>>) -> Option<Bounds> {
+        if index == 0 {
+            return None;
+        }
+        if let Some(bounds) = memo.get(&index) {
+            return *bounds;
+        }
+        let bounds = match self.nodes[index as usize] {
+            Node::Leaf(rows) => {
+                let mut bounds: Option<Bounds> = None;
+                for (y, row) in rows.iter().enumerate() {
+                    for x in 0..8 {
+                        if row >> x & 1 == 1 {
+                            let cell = Bounds { min_x: x, min_y: y as u64, max_x: x, max_y: y as u64 };
+                            bounds = Some(bounds.map_or(cell, |b| b.union(cell)));
+                        }
+                    }
+                }
+                bounds
+            }
+            Node::Branch { level, children } => {
+                let half = 1u64 << (level - 1);
+                let mut bounds: Option<Bounds> = None;
+                for (i, child) in children.iter().enumerate() {
+                    if let Some(child_bounds) = self.node_bounds(*child, memo) {
+                        let child_bounds = child_bounds.offset((i as u64 % 2) * half, (i as u64 / 2) * half);
+                        bounds = Some(bounds.map_or(child_bounds, |b| b.union(child_bounds)));
+                    }
+                }
+                bounds
+            }
+        };
+        memo.insert(index, bounds);
+        bounds
+    }
+
+    fn population(&self, index: u32, memo: &mut HashMap<u32, u64>) -> u64 {
+        if index == 0 {
+            return 0;
+        }
+        if let Some(population) = memo.get(&index) {
+            return *population;
+        }
+        let population = match self.nodes[index as usize] {
+            Node::Leaf(rows) => rows.iter().map(|row| row.count_ones() as u64).sum(),
+            Node::Branch { children, .. } => children
+                .iter()
+                .map(|child| self.population(*child, memo))
+                .fold(0u64, |acc, p| acc.saturating_add(p)),
+        };
+        memo.insert(index, population);
+        population
+    }
+
+    fn fill_window(&self, index: u32, node_x: u64, node_y: u64, window: &Window, pattern: &mut Pattern) {
+        if index == 0 {
+            return;
+        }
+        let size = 1u64 << self.level(index);
+        if !window.intersects(node_x, node_y, size) {
+            return;
+        }
+        match self.nodes[index as usize] {
+            Node::Leaf(rows) => {
+                for (y, row) in rows.iter().enumerate() {
+                    for x in 0..8 {
+                        if row >> x & 1 == 0 {
+                            continue;
+                        }
+                        let (cell_x, cell_y) = (node_x + x, node_y + y as u64);
+                        if window.contains(cell_x, cell_y) {
+                            let pattern_index = (cell_y - window.y) * pattern.width as u64 + (cell_x - window.x);
+                            pattern.data[pattern_index as usize] = 1;
+                        }
+                    }
+                }
+            }
+            Node::Branch { children, .. } => {
+                let half = size / 2;
+                for (i, child) in children.iter().enumerate() {
+                    let child_x = node_x + (i as u64 % 2) * half;
+                    let child_y = node_y + (i as u64 / 2) * half;
+                    self.fill_window(*child, child_x, child_y, window, pattern);
+                }
+            }
+        }
+    }
+}
+
+struct Window {
+    x: u64,
+    y: u64,
+    width: u64,
+    height: u64,
+}
+
+impl Window {
+    fn intersects(&self, x: u64, y: u64, size: u64) -> bool {
+        x < self.x.saturating_add(self.width)
+            && y < self.y.saturating_add(self.height)
+            && x.saturating_add(size) > self.x
+            && y.saturating_add(size) > self.y
+    }
+    fn contains(&self, x: u64, y: u64) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+}
+
+struct Builder<'a> {
+    pattern: &'a Pattern,
+    nodes: Vec<Node>,
+    indices: HashMap<Node, u32>,
+}
+
+impl Builder<'_> {
+    fn cell(&self, x: u32, y: u32) -> bool {
+        x < self.pattern.width
+            && y < self.pattern.height
+            && self.pattern.data[(y * self.pattern.width + x) as usize] != 0
+    }
+
+    /// Children are always added before parents, which is the order macrocell file expects
+    fn build(&mut self, level: u32, x: u32, y: u32) -> u32 {
+        if x >= self.pattern.width || y >= self.pattern.height {
+            return 0;
+        }
+        let node = if level == LEAF_LEVEL {
+            let mut rows = [0u8; 8];
+            for (row_y, row) in rows.iter_mut().enumerate() {
+                for row_x in 0..8 {
+                    if self.cell(x + row_x, y + row_y as u32) {
+                        *row |= 1 << row_x;
+                    }
+                }
+            }
+            if rows == [0; 8] {
+                return 0;
+            }
+            Node::Leaf(rows)
+        } else {
+            let half = 1 << (level - 1);
+            let children = [
+                self.build(level - 1, x, y),
+                self.build(level - 1, x + half, y),
+                self.build(level - 1, x, y + half),
+                self.build(level - 1, x + half, y + half),
+            ];
+            if children == [0; 4] {
+                return 0;
+            }
+            Node::Branch { level, children }
+        };
+        if let Some(index) = self.indices.get(&node) {
+            return *index;
+        }
+        self.nodes.push(node);
+        let index = (self.nodes.len() - 1) as u32;
+        self.indices.insert(node, index);
+        index
+    }
+}
+
+fn parse_leaf(line: &str, line_number: usize) -> Result<Node, PatternError> {
+    let mut rows = [0u8; 8];
+    let mut y = 0;
+    let mut x = 0;
+    for char in line.chars() {
+        if y >= 8 {
+            return Err(PatternError::parse(line_number, "leaf has more than 8 rows"));
+        }
+        match char {
+            '.' => x += 1,
+            '*' => {
+                if x < 8 {
+                    rows[y] |= 1 << x;
+                }
+                x += 1;
+            }
+            '$' => {
+                y += 1;
+                x = 0;
+                continue;
+            }
+            _ => {
+                return Err(PatternError::parse(
+                    line_number,
+                    format!("unexpected character '{}' in leaf", char),
+                ))
+            }
+        }
+        if x > 8 {
+            return Err(PatternError::parse(line_number, "leaf row is longer than 8 cells"));
+        }
+    }
+    Ok(Node::Leaf(rows))
+}
+
+fn parse_branch(line: &str, line_number: usize, nodes: &[Node]) -> Result<Node, PatternError> {
+    let numbers: Vec<u64> = line
+        .split_whitespace()
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .map_err(|_| PatternError::parse(line_number, format!("invalid number \"{}\"", value)))
+        })
+        .collect::<Result<_, _>>()?;
+    let [level, children @ ..] = numbers.as_slice() else {
+        unreachable!("line is not empty");
+    };
+    if children.len() != 4 {
+        return Err(PatternError::parse(
+            line_number,
+            "expected node as \"level nw ne sw se\"",
+        ));
+    }
+    let level = *level as u32;
+    if level <= LEAF_LEVEL {
+        return Err(PatternError::parse(
+            line_number,
+            format!("node level {} is not supported, multi-state macrocell files can't be loaded", level),
+        ));
+    }
+    if level > MAX_LEVEL {
+        return Err(PatternError::parse(line_number, format!("node level {} is too big", level)));
+    }
+    let mut node_children = [0u32; 4];
+    for (child, node_child) in children.iter().zip(node_children.iter_mut()) {
+        if *child as usize >= nodes.len() {
+            return Err(PatternError::parse(
+                line_number,
+                format!("node references node {} which is not defined yet", child),
+            ));
+        }
+        let child_level = match nodes[*child as usize] {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Branch { level, .. } => level,
+        };
+        if *child != 0 && child_level != level - 1 {
+            return Err(PatternError::parse(
+                line_number,
+                format!("child {} has level {}, expected {}", child, child_level, level - 1),
+            ));
+        }
+        *node_child = *child as u32;
+    }
+    Ok(Node::Branch { level, children: node_children })
+}
+
+fn check_rule(rule: &str, line_number: usize) -> Result<(), PatternError> {
+    let normalized = rule.trim().to_ascii_uppercase();
+    if normalized == "B3/S23" || normalized == "23/3" {
+        Ok(())
+    } else {
+        Err(PatternError::parse(
+            line_number,
+            format!("unsupported rule \"{}\", only B3/S23 is supported", rule),
+        ))
+    }
+}
+
+/// Whole pattern as dense pattern, used when macrocell is imported as ordinary pattern
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let macrocell = Macrocell::parse(text)?;
+    let (width, height) = macrocell.content_size();
+    if width as i64 > MAX_PATTERN_SIDE || height as i64 > MAX_PATTERN_SIDE {
+        return Err(PatternError::parse(
+            0,
+            format!(
+                "pattern is too big ({}x{}), maximum side is {}",
+                width, height, MAX_PATTERN_SIDE
+            ),
+        ));
+    }
+    Ok(macrocell.window(0, 0, width as u32, height as u32))
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    Macrocell::from_pattern(pattern).write()
+}
@@ -0,0 +1,199 @@
+mod image;
+mod life_105;
+mod life_106;
+mod macrocell;
+mod plaintext;
+mod rle;
+
+pub use image::{is_png, load_png, save_png, Binarization, ImageImportOptions, ImagePalette};
+pub use macrocell::Macrocell;
+
+use crate::gol_manager::patterns::Pattern;
+use std::fmt;
+use std::path::Path;
+
+/// Patterns bigger than this in any dimension are rejected, they would not fit into the game texture anyway
+pub const MAX_PATTERN_SIDE: i64 = 16384;
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(std::io::Error),
+    /// Line numbers start from 1, 0 means the error is about the whole file
+    Parse { line: usize, message: String },
+    UnknownFormat,
+    /// PNG decoding or encoding failed
+    Image(String),
+}
+
+impl PatternError {
+    fn parse(line: usize, message: impl Into<String>) -> Self {
+        PatternError::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(err) => write!(f, "{}", err),
+            PatternError::Parse { line: 0, message } => write!(f, "{}", message),
+            PatternError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            PatternError::UnknownFormat => write!(f, "unknown pattern format"),
+            PatternError::Image(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<std::io::Error> for PatternError {
+    fn from(err: std::io::Error) -> Self {
+        PatternError::Io(err)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PatternFormat {
+    /// Run length encoded .rle, the most common format on LifeWiki and in Golly
+    Rle,
+    /// LifeWiki .cells
+    Plaintext,
+    Life105,
+    Life106,
+    /// Golly .mc, can hold patterns much bigger than the game texture, see Macrocell
+    Macrocell,
+}
+
+impl PatternFormat {
+    pub const ALL: [PatternFormat; 5] = [
+        PatternFormat::Rle,
+        PatternFormat::Plaintext,
+        PatternFormat::Life105,
+        PatternFormat::Life106,
+        PatternFormat::Macrocell,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatternFormat::Rle => "RLE (.rle)",
+            PatternFormat::Plaintext => "Plaintext (.cells)",
+            PatternFormat::Life105 => "Life 1.05 (.lif)",
+            PatternFormat::Life106 => "Life 1.06 (.lif)",
+            PatternFormat::Macrocell => "Macrocell (.mc)",
+        }
+    }
+
+    /// Life 1.05 and 1.06 share the extension, so header of the file is checked first
+    pub fn detect(path: &Path, text: &str) -> Option<Self> {
+        let first_line = text.lines().next().unwrap_or("").trim();
+        if first_line.starts_with(life_105::HEADER) {
+            return Some(PatternFormat::Life105);
+        }
+        if first_line.starts_with(life_106::HEADER) {
+            return Some(PatternFormat::Life106);
+        }
+        if first_line.starts_with(macrocell::HEADER) {
+            return Some(PatternFormat::Macrocell);
+        }
+        // text pasted from the clipboard has no extension, .cells starts with "!" comments or is just rows of cells
+        let is_plaintext = first_line.starts_with('!')
+            || (!text.trim().is_empty() && text.lines().all(|line| line.trim().chars().all(|c| matches!(c, '.' | 'O' | '*'))));
+        if is_plaintext {
+            return Some(PatternFormat::Plaintext);
+        }
+        // RLE starts with "x = ..." after the "#" lines
+        let first_rle_line = text.lines().map(|line| line.trim()).find(|line| !line.is_empty() && !line.starts_with('#'));
+        if first_rle_line.is_some_and(|line| line.starts_with('x') && line.contains('=')) {
+            return Some(PatternFormat::Rle);
+        }
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("rle") => Some(PatternFormat::Rle),
+            Some("cells") => Some(PatternFormat::Plaintext),
+            Some("mc") => Some(PatternFormat::Macrocell),
+            _ => None,
+        }
+    }
+}
+
+/// Coordinate based formats can describe a huge pattern with a few cells, they are rejected when
+/// the bounding box is bigger than max_size (usually the grid size), since the parsed pattern is dense
+pub fn parse_pattern(text: &str, format: PatternFormat, max_size: (u32, u32)) -> Result<Pattern, PatternError> {
+    match format {
+        PatternFormat::Rle => rle::parse(text),
+        PatternFormat::Plaintext => plaintext::parse(text),
+        PatternFormat::Life105 => life_105::parse(text, max_size),
+        PatternFormat::Life106 => life_106::parse(text, max_size),
+        PatternFormat::Macrocell => macrocell::parse(text),
+    }
+}
+
+pub fn write_pattern(pattern: &Pattern, format: PatternFormat) -> String {
+    match format {
+        PatternFormat::Rle => rle::write(pattern),
+        PatternFormat::Plaintext => plaintext::write(pattern),
+        PatternFormat::Life105 => life_105::write(pattern),
+        PatternFormat::Life106 => life_106::write(pattern),
+        PatternFormat::Macrocell => macrocell::write(pattern),
+    }
+}
+
+/// Returns detected format together with file contents, so caller can decide how to parse it (e.g. keep macrocell sparse)
+pub fn read_pattern_file(path: &Path) -> Result<(PatternFormat, String), PatternError> {
+    let text = std::fs::read_to_string(path)?;
+    let format = PatternFormat::detect(path, &text).ok_or(PatternError::UnknownFormat)?;
+    Ok((format, text))
+}
+
+/// Loads any supported file as dense pattern, PNG images are converted with the given options. See parse_pattern() for max_size
+pub fn load_pattern(path: &Path, image_options: &ImageImportOptions, max_size: (u32, u32)) -> Result<Pattern, PatternError> {
+    if is_png(path) {
+        return load_png(path, image_options);
+    }
+    let (format, text) = read_pattern_file(path)?;
+    parse_pattern(&text, format, max_size)
+}
+
+pub fn save_pattern(path: &Path, pattern: &Pattern, format: PatternFormat) -> Result<(), PatternError> {
+    std::fs::write(path, write_pattern(pattern, format))?;
+    Ok(())
+}
+
+/// Coordinate based formats (Life 1.05, 1.06) share this to turn list of cells into pattern
+fn pattern_from_cells(
+    cells: &[(i64, i64)],
+    name: Option<String>,
+    comments: Vec<String>,
+    (max_width, max_height): (u32, u32),
+) -> Result<Pattern, PatternError> {
+    if cells.is_empty() {
+        return Err(PatternError::parse(0, "pattern contains no alive cells"));
+    }
+    let min_x = cells.iter().map(|c| c.0).min().unwrap();
+    let max_x = cells.iter().map(|c| c.0).max().unwrap();
+    let min_y = cells.iter().map(|c| c.1).min().unwrap();
+    let max_y = cells.iter().map(|c| c.1).max().unwrap();
+    let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+    let max_width = (max_width as i64).min(MAX_PATTERN_SIDE);
+    let max_height = (max_height as i64).min(MAX_PATTERN_SIDE);
+    if width > max_width || height > max_height {
+        return Err(PatternError::parse(
+            0,
+            format!("pattern is too big ({}x{}), maximum is {}x{}", width, height, max_width, max_height),
+        ));
+    }
+    let mut pattern = Pattern::from_alive_cells(cells);
+    pattern.name = name;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+/// Name is not part of Life 1.0x formats (and RLE "#C" lines written by some tools), so it's stored as "Name: ..." description line, same way as in .cells
+fn split_name_comment(comment: &str) -> Option<&str> {
+    comment.strip_prefix("Name:").map(|name| name.trim())
+}
@@ -0,0 +1,85 @@
+use super::{split_name_comment, PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+
+// https://conwaylife.com/wiki/Plaintext
+// "!" starts a comment line, "." is dead cell and "O" is alive cell. Rows can have different lengths, missing cells are dead
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut rows: Vec<Vec<u8>> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        if let Some(comment) = line.strip_prefix('!') {
+            match split_name_comment(comment) {
+                Some(pattern_name) => name = Some(pattern_name.to_string()),
+                None => comments.push(comment.trim_end().to_string()),
+            }
+            continue;
+        }
+        let mut row = Vec::new();
+        for (column, char) in line.trim_end().chars().enumerate() {
+            let value = match char {
+                '.' => 0,
+                // some older files use * instead of O
+                'O' | '*' => 1,
+                _ => {
+                    return Err(PatternError::parse(
+                        line_number,
+                        format!("unexpected character '{}' at column {}, expected '.' or 'O'", char, column + 1),
+                    ))
+                }
+            };
+            row.push(value);
+        }
+        if row.len() as i64 > MAX_PATTERN_SIDE {
+            return Err(PatternError::parse(
+                line_number,
+                format!("row is longer than {} cells", MAX_PATTERN_SIDE),
+            ));
+        }
+        rows.push(row);
+    }
+
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    if rows.is_empty() {
+        return Err(PatternError::parse(0, "pattern has no cell rows"));
+    }
+    if rows.len() as i64 > MAX_PATTERN_SIDE {
+        return Err(PatternError::parse(
+            0,
+            format!("pattern has more than {} rows", MAX_PATTERN_SIDE),
+        ));
+    }
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap();
+    let height = rows.len();
+    let mut data = vec![0; width * height];
+    for (y, row) in rows.iter().enumerate() {
+        data[y * width..y * width + row.len()].copy_from_slice(row);
+    }
+
+    let mut pattern = Pattern::new(data, width as u32, height as u32);
+    pattern.name = name;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+/// Rows are written in full width, so pattern dimensions survive the round trip
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("!{}\n", comment));
+    }
+    for row in pattern.data.chunks(pattern.width.max(1) as usize) {
+        out.extend(row.iter().map(|v| if *v != 0 { 'O' } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,205 @@
+use super::{split_name_comment, PatternError, MAX_PATTERN_SIDE};
+use crate::gol_manager::patterns::Pattern;
+
+// https://conwaylife.com/wiki/Run_Length_Encoded
+// "#N" is the name, "#C" (or "#c") comments, "#O" author. Header is "x = 3, y = 3, rule = B3/S23".
+// Cells are runs like "3o" (alive) or "2b" (dead), "$" ends a row and "!" ends the pattern
+
+const MAX_LINE_LENGTH: usize = 70;
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut size = None;
+    let mut data = Vec::new();
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut run = String::new();
+    let mut finished = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if finished {
+            // everything after "!" is free text
+            break;
+        }
+        if let Some(directive) = line.strip_prefix('#') {
+            let kind = directive.chars().next().unwrap_or(' ');
+            let argument = directive[kind.len_utf8().min(directive.len())..].trim();
+            match kind {
+                'N' => name = Some(argument.to_string()),
+                'C' | 'c' => match split_name_comment(argument) {
+                    Some(pattern_name) => name = Some(pattern_name.to_string()),
+                    None => comments.push(argument.to_string()),
+                },
+                'O' => comments.push(format!("Author: {}", argument)),
+                // position and rule lines of old tools
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let Some((width, height)) = size else {
+            let (width, height) = parse_header(line, line_number)?;
+            size = Some((width, height));
+            data = vec![0; width as usize * height as usize];
+            continue;
+        };
+
+        for char in line.chars() {
+            if char.is_ascii_digit() {
+                run.push(char);
+                continue;
+            }
+            if char.is_whitespace() {
+                continue;
+            }
+            let count: u32 = if run.is_empty() {
+                1
+            } else {
+                run.parse()
+                    .ok()
+                    .filter(|count| *count as i64 <= MAX_PATTERN_SIDE)
+                    .ok_or_else(|| PatternError::parse(line_number, format!("run length {} is too big", run)))?
+            };
+            run.clear();
+            // runs are limited, but there can be any number of them
+            let advance = |position: u32| {
+                position
+                    .checked_add(count)
+                    .ok_or_else(|| PatternError::parse(line_number, "cells are too far from the origin"))
+            };
+            match char {
+                'b' | '.' => x = advance(x)?,
+                'o' | 'A' => {
+                    let end = advance(x)?;
+                    if end > width || y >= height {
+                        return Err(PatternError::parse(
+                            line_number,
+                            format!("cells are outside of the {}x{} size from the header", width, height),
+                        ));
+                    }
+                    let row_start = (y * width + x) as usize;
+                    data[row_start..row_start + count as usize].fill(1);
+                    x = end;
+                }
+                '$' => {
+                    y = advance(y)?;
+                    x = 0;
+                }
+                '!' => {
+                    finished = true;
+                    break;
+                }
+                _ => {
+                    return Err(PatternError::parse(
+                        line_number,
+                        format!("unexpected character '{}', only two-state patterns are supported", char),
+                    ))
+                }
+            }
+        }
+    }
+
+    let Some((width, height)) = size else {
+        return Err(PatternError::parse(0, "\"x = ..., y = ...\" header is missing"));
+    };
+    if !finished {
+        return Err(PatternError::parse(0, "pattern is not terminated with '!'"));
+    }
+    let mut pattern = Pattern::new(data, width, height);
+    pattern.name = name;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+fn parse_header(line: &str, line_number: usize) -> Result<(u32, u32), PatternError> {
+    let mut width = None;
+    let mut height = None;
+    for part in line.split(',') {
+        let Some((key, value)) = part.split_once('=') else {
+            return Err(PatternError::parse(
+                line_number,
+                format!("expected \"x = ..., y = ...\" header, got \"{}\"", line),
+            ));
+        };
+        let value = value.trim();
+        let parse_size = || {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|v| *v as i64 <= MAX_PATTERN_SIDE)
+                .ok_or_else(|| PatternError::parse(line_number, format!("invalid size \"{}\"", value)))
+        };
+        match key.trim() {
+            "x" => width = Some(parse_size()?),
+            "y" => height = Some(parse_size()?),
+            "rule" => {
+                let rule = value.to_ascii_uppercase();
+                if rule != "B3/S23" && rule != "23/3" {
+                    return Err(PatternError::parse(
+                        line_number,
+                        format!("unsupported rule \"{}\", only B3/S23 is supported", value),
+                    ));
+                }
+            }
+            other => {
+                return Err(PatternError::parse(
+                    line_number,
+                    format!("unknown header field \"{}\"", other),
+                ))
+            }
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(PatternError::parse(line_number, "header must have both x and y")),
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    out.push_str(&format!("x = {}, y = {}, rule = B3/S23\n", pattern.width, pattern.height));
+
+    // runs of (count, tag), empty rows are merged into "n$"
+    let mut runs: Vec<(u32, char)> = Vec::new();
+    let push_run = |runs: &mut Vec<(u32, char)>, count: u32, tag: char| match runs.last_mut() {
+        Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+        _ => runs.push((count, tag)),
+    };
+    for (y, row) in pattern.data.chunks(pattern.width.max(1) as usize).enumerate() {
+        if y > 0 {
+            push_run(&mut runs, 1, '$');
+        }
+        let last_alive = row.iter().rposition(|v| *v != 0).map_or(0, |i| i + 1);
+        for cell in &row[..last_alive] {
+            push_run(&mut runs, 1, if *cell != 0 { 'o' } else { 'b' });
+        }
+    }
+    while runs.last().is_some_and(|(_, tag)| *tag == '$') {
+        runs.pop();
+    }
+    runs.push((1, '!'));
+
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let item = if count == 1 { tag.to_string() } else { format!("{}{}", count, tag) };
+        if line.len() + item.len() > MAX_LINE_LENGTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
@@ -0,0 +1,220 @@
+use crate::gol_manager::patterns::{self, Pattern};
+use crate::pattern_io::{self, Binarization, ImageImportOptions, PatternError};
+use std::path::{Path, PathBuf};
+
+/// Scanned on startup if it exists in the working directory
+pub const DEFAULT_DIRECTORY: &str = "patterns";
+pub const BUILT_IN_TAG: &str = "built-in";
+
+const EXTENSIONS: [&str; 3] = ["rle", "cells", "png"];
+/// Symlinked directories could loop forever otherwise
+const MAX_DIRECTORY_DEPTH: usize = 8;
+/// Library images are imported one cell per pixel, only bigger ones are scaled down
+const MAX_IMAGE_SIDE: u32 = 1024;
+/// Bounding box limit of coordinate based patterns, they are kept dense like all library patterns
+const MAX_SPARSE_SIDE: u32 = 4096;
+
+pub enum LibrarySource {
+    BuiltIn,
+    File(PathBuf),
+}
+
+pub struct LibraryEntry {
+    pub name: String,
+    /// Category of built-ins, subdirectories and file format of user patterns
+    pub tags: Vec<String>,
+    pub pattern: Pattern,
+    pub source: LibrarySource,
+}
+
+impl LibraryEntry {
+    /// Every word of the query has to be in the name, tags or comments, case insensitive
+    pub fn matches(&self, query: &str) -> bool {
+        let text = std::iter::once(&self.name)
+            .chain(&self.tags)
+            .chain(&self.pattern.comments)
+            .map(|text| text.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        query.to_lowercase().split_whitespace().all(|word| text.contains(word))
+    }
+}
+
+/// Built-in patterns together with patterns from user directories (.rle, .cells, .png)
+pub struct PatternLibrary {
+    entries: Vec<LibraryEntry>,
+    directories: Vec<PathBuf>,
+    /// Files that failed to load in the last scan
+    errors: Vec<(PathBuf, PatternError)>,
+}
+
+impl PatternLibrary {
+    pub fn new() -> Self {
+        let mut library = Self {
+            entries: Vec::new(),
+            directories: Vec::new(),
+            errors: Vec::new(),
+        };
+        if Path::new(DEFAULT_DIRECTORY).is_dir() {
+            library.directories.push(PathBuf::from(DEFAULT_DIRECTORY));
+        }
+        library.rescan();
+        library
+    }
+
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.entries
+    }
+
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    pub fn errors(&self) -> &[(PathBuf, PatternError)] {
+        &self.errors
+    }
+
+    /// All tags sorted, built-in categories first
+    pub fn tags(&self) -> Vec<&str> {
+        let (built_ins, files): (Vec<&LibraryEntry>, Vec<&LibraryEntry>) = self
+            .entries
+            .iter()
+            .partition(|entry| matches!(entry.source, LibrarySource::BuiltIn));
+        let mut tags: Vec<&str> = Vec::new();
+        for tag in built_ins.iter().flat_map(|entry| &entry.tags) {
+            if !tags.contains(&tag.as_str()) {
+                tags.push(tag);
+            }
+        }
+        let mut file_tags: Vec<&str> = files
+            .iter()
+            .flat_map(|entry| &entry.tags)
+            .map(|tag| tag.as_str())
+            .filter(|tag| !tags.contains(tag))
+            .collect();
+        file_tags.sort_unstable();
+        file_tags.dedup();
+        tags.extend(file_tags);
+        tags
+    }
+
+    /// Returns number of patterns loaded from it
+    pub fn add_directory(&mut self, path: PathBuf) -> Result<usize, PatternError> {
+        if !path.is_dir() {
+            return Err(PatternError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not a directory", path.display()),
+            )));
+        }
+        if !self.directories.contains(&path) {
+            self.directories.push(path.clone());
+        }
+        self.rescan();
+        let count = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(&entry.source, LibrarySource::File(file) if file.starts_with(&path)))
+            .count();
+        Ok(count)
+    }
+
+    pub fn remove_directory(&mut self, path: &Path) {
+        self.directories.retain(|directory| directory != path);
+        self.rescan();
+    }
+
+    /// Reloads all directories, e.g. after files were added
+    pub fn rescan(&mut self) {
+        self.entries = built_in_entries();
+        self.errors.clear();
+        for directory in self.directories.clone() {
+            let mut files = Vec::new();
+            collect_files(&directory, 0, &mut files);
+            files.sort();
+            for file in files {
+                match load_entry(&directory, &file) {
+                    Ok(entry) => self.entries.push(entry),
+                    Err(err) => self.errors.push((file, err)),
+                }
+            }
+        }
+    }
+}
+
+fn built_in_entries() -> Vec<LibraryEntry> {
+    let built_ins = [
+        (patterns::get_blinker(), "oscillator"),
+        (patterns::get_toad(), "oscillator"),
+        (patterns::get_penta_decathlon(), "oscillator"),
+        (patterns::get_loaf(), "still life"),
+        (patterns::get_light_weight_spaceship(), "spaceship"),
+        (patterns::get_middle_weight_spaceship(), "spaceship"),
+        (patterns::get_heavy_weight_spaceship(), "spaceship"),
+    ];
+    built_ins
+        .into_iter()
+        .map(|(pattern, category)| LibraryEntry {
+            name: pattern.name.clone().unwrap_or_default(),
+            tags: vec![category.to_string(), BUILT_IN_TAG.to_string()],
+            pattern: pattern.clone(),
+            source: LibrarySource::BuiltIn,
+        })
+        .collect()
+}
+
+/// Unreadable subdirectories are skipped silently, the user only picks the top one
+fn collect_files(directory: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(read_dir) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for path in read_dir.flatten().map(|entry| entry.path()) {
+        let is_hidden = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'));
+        if is_hidden {
+            continue;
+        }
+        if path.is_dir() {
+            if depth < MAX_DIRECTORY_DEPTH {
+                collect_files(&path, depth + 1, files);
+            }
+        } else if get_extension(&path).is_some_and(|ext| EXTENSIONS.contains(&ext.as_str())) {
+            files.push(path);
+        }
+    }
+}
+
+fn load_entry(directory: &Path, file: &Path) -> Result<LibraryEntry, PatternError> {
+    let pattern = if pattern_io::is_png(file) {
+        let options = ImageImportOptions {
+            max_width: MAX_IMAGE_SIDE,
+            max_height: MAX_IMAGE_SIDE,
+            upscale: false,
+            binarization: Binarization::Threshold,
+            threshold: 0.5,
+            invert: false,
+        };
+        pattern_io::load_png(file, &options)?
+    } else {
+        let (format, text) = pattern_io::read_pattern_file(file)?;
+        pattern_io::parse_pattern(&text, format, (MAX_SPARSE_SIDE, MAX_SPARSE_SIDE))?
+    };
+    let file_stem = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+    // "spaceships/c4/glider.rle" is tagged "spaceships", "c4" and "rle"
+    let mut tags: Vec<String> = file
+        .parent()
+        .and_then(|parent| parent.strip_prefix(directory).ok())
+        .map(|relative| relative.iter().map(|part| part.to_string_lossy().to_lowercase()).collect())
+        .unwrap_or_default();
+    tags.extend(get_extension(file));
+
+    Ok(LibraryEntry {
+        name: pattern.name.clone().filter(|name| !name.is_empty()).unwrap_or(file_stem),
+        tags,
+        pattern,
+        source: LibrarySource::File(file.to_path_buf()),
+    })
+}
+
+fn get_extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase())
+}
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 pub struct PerfMonitor {
     clocks: HashMap<String, Instant>,
@@ -39,17 +39,19 @@ impl PerfMonitor {
         self.clocks.insert(label.to_string(), Instant::now());
     }
 
+    #[allow(dead_code)]
     pub fn end(&mut self, label: &str) {
         self.clocks.remove(label);
     }
 
+    #[allow(dead_code)]
     pub fn get_summary(&self) -> String {
         self.ms_per_frames.iter().fold("".to_string(), |acc, (label, ms_per_frame)| {
-            format!("{}: {:.1} fps\n", &label, 1000.0 / ms_per_frame)
+            format!("{}{}: {:.1} fps\n", acc, &label, 1000.0 / ms_per_frame)
         })
     }
 
     pub fn get_ms_per_frame(&self, label: &str) -> Option<f32> {
-        self.ms_per_frames.get(label).map(|ms| *ms)
+        self.ms_per_frames.get(label).copied()
     }
 }
\ No newline at end of file
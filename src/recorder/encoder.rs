@@ -0,0 +1,92 @@
+use super::{AnimationFormat, RecordingSettings};
+use crate::gol::{pack_cells, unpack_cells};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc;
+
+/// Runs on the encoder thread until the sender is dropped. Returns number of written frames
+pub fn encode(settings: RecordingSettings, receiver: mpsc::Receiver<Vec<u8>>) -> Result<u32, String> {
+    match settings.format {
+        AnimationFormat::Gif => encode_gif(&settings, receiver),
+        AnimationFormat::Apng => encode_apng(&settings, receiver),
+    }
+}
+
+/// GIF frames are written as they come
+fn encode_gif(settings: &RecordingSettings, receiver: mpsc::Receiver<Vec<u8>>) -> Result<u32, String> {
+    let (width, height) = scaled_size(settings);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(format!("GIF can't be bigger than {}x{}", u16::MAX, u16::MAX));
+    }
+    let file = File::create(&settings.path).map_err(|err| err.to_string())?;
+    let palette = settings.colors.concat();
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &palette)
+        .map_err(|err| err.to_string())?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(|err| err.to_string())?;
+
+    let mut frame_count = 0;
+    for cells in receiver {
+        let frame = gif::Frame {
+            width: width as u16,
+            height: height as u16,
+            // GIF delay is in hundredths of a second, viewers play 0 and 1 at about 10 fps, so 2 is the shortest
+            delay: (settings.frame_delay_ms.saturating_add(5) / 10).clamp(2, u16::MAX as u32) as u16,
+            buffer: Cow::Owned(scale_frame(&cells, settings)),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+        frame_count += 1;
+    }
+    Ok(frame_count)
+}
+
+/// APNG needs frame count in the header, so frames are kept (packed to bits) until recording stops
+fn encode_apng(settings: &RecordingSettings, receiver: mpsc::Receiver<Vec<u8>>) -> Result<u32, String> {
+    let frames: Vec<Vec<u8>> = receiver.iter().map(|cells| pack_cells(&cells)).collect();
+    if frames.is_empty() {
+        return Err("no frames were captured".to_string());
+    }
+    let (width, height) = scaled_size(settings);
+    let file = File::create(&settings.path).map_err(|err| err.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(settings.colors.concat());
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(|err| err.to_string())?;
+    encoder
+        .set_frame_delay(settings.frame_delay_ms.min(u16::MAX as u32) as u16, 1000)
+        .map_err(|err| err.to_string())?;
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    let cell_count = (settings.region.2 * settings.region.3) as usize;
+    for packed in &frames {
+        let cells = unpack_cells(packed, cell_count);
+        writer
+            .write_image_data(&scale_frame(&cells, settings))
+            .map_err(|err| err.to_string())?;
+    }
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(frames.len() as u32)
+}
+
+fn scaled_size(settings: &RecordingSettings) -> (u32, u32) {
+    (settings.region.2 * settings.scale, settings.region.3 * settings.scale)
+}
+
+/// Turns cells into palette indices, every cell becomes scale x scale block
+fn scale_frame(cells: &[u8], settings: &RecordingSettings) -> Vec<u8> {
+    let (region_width, scale) = (settings.region.2 as usize, settings.scale as usize);
+    let mut pixels = Vec::with_capacity(cells.len() * scale * scale);
+    for row in cells.chunks(region_width) {
+        let scaled_row: Vec<u8> = row
+            .iter()
+            .flat_map(|cell| std::iter::repeat_n((*cell != 0) as u8, scale))
+            .collect();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&scaled_row);
+        }
+    }
+    pixels
+}
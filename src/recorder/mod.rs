@@ -0,0 +1,174 @@
+mod encoder;
+
+use crate::gol::{GoL, PendingRead};
+use egui_wgpu::wgpu;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Maximum number of readbacks that are not finished yet, see RecordingSettings::drop_frames_when_busy
+const MAX_PENDING_READS: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "GIF",
+            AnimationFormat::Apng => "APNG",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordingSettings {
+    pub path: PathBuf,
+    pub format: AnimationFormat,
+    /// x, y, width, height in cells
+    pub region: (u32, u32, u32, u32),
+    /// Frame is captured every this many generations
+    pub every_generations: u32,
+    /// Each cell becomes scale x scale pixels
+    pub scale: u32,
+    pub frame_delay_ms: u32,
+    /// sRGB colours of dead and alive cells, see ImagePalette::colors()
+    pub colors: Vec<[u8; 3]>,
+    /// When GPU falls behind, interactive mode drops frames to keep simulation smooth, headless mode waits instead
+    pub drop_frames_when_busy: bool,
+}
+
+impl RecordingSettings {
+    /// Region has to be non-empty and inside the grid, otherwise the readback would copy out of the texture
+    pub fn check_region(&self, (gol_width, gol_height): (u32, u32)) -> Result<(), String> {
+        let (x, y, width, height) = self.region;
+        if width == 0 || height == 0 || x as u64 + width as u64 > gol_width as u64 || y as u64 + height as u64 > gol_height as u64 {
+            return Err(format!("region must be inside the {}x{} grid", gol_width, gol_height));
+        }
+        Ok(())
+    }
+}
+
+/// Captures frames of the simulation into animated image.
+/// Frames are read from GPU without waiting, and encoded on a separate thread, so recording doesn't slow down the simulation.
+pub struct Recorder {
+    settings: RecordingSettings,
+    pending_reads: VecDeque<PendingRead>,
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    encoder_thread: Option<JoinHandle<Result<u32, String>>>,
+    /// Generation of the next frame, None until the first frame is captured
+    next_generation: Option<u64>,
+    captured_frames: u32,
+    dropped_frames: u32,
+    /// Failed readback stops the recording, frames captured before it are still saved
+    read_error: Option<String>,
+}
+
+impl Recorder {
+    pub fn start(settings: RecordingSettings) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let encoder_settings = settings.clone();
+        let encoder_thread = std::thread::spawn(move || encoder::encode(encoder_settings, receiver));
+        Self {
+            settings,
+            pending_reads: VecDeque::new(),
+            sender: Some(sender),
+            encoder_thread: Some(encoder_thread),
+            next_generation: None,
+            captured_frames: 0,
+            dropped_frames: 0,
+            read_error: None,
+        }
+    }
+
+    /// Call right after recording starts and after every generation, recorder decides itself if the frame should be captured
+    pub fn on_generation(&mut self, gol: &GoL, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let generation = gol.get_generation();
+        if self.sender.is_none() || self.next_generation.is_some_and(|next| generation < next) {
+            return;
+        }
+        self.next_generation = Some(generation + self.settings.every_generations as u64);
+        if self.pending_reads.len() >= MAX_PENDING_READS {
+            if self.settings.drop_frames_when_busy {
+                self.dropped_frames += 1;
+                return;
+            }
+            device.poll(wgpu::Maintain::Wait);
+            self.poll(device);
+        }
+        let (x, y, width, height) = self.settings.region;
+        self.pending_reads
+            .push_back(gol.start_area_read(device, queue, x, y, width, height));
+    }
+
+    /// Passes finished readbacks to the encoder. Should be called every frame
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Poll);
+        // reads finish in submission order, so frames stay in order
+        while let Some(result) = self.pending_reads.front().and_then(|read| read.try_take()) {
+            self.pending_reads.pop_front();
+            let data = match result {
+                Ok(data) => data,
+                Err(err) => {
+                    self.read_error = Some(err);
+                    self.pending_reads.clear();
+                    self.sender = None;
+                    return;
+                }
+            };
+            if let Some(sender) = &self.sender {
+                // encoder thread is gone only if it failed, error is reported from finish()
+                let _ = sender.send(data);
+                self.captured_frames += 1;
+            }
+        }
+    }
+
+    /// Stops capturing. Already captured frames are still encoded, check is_finished() / take_result()
+    pub fn stop(&mut self, device: &wgpu::Device) {
+        if self.sender.is_none() {
+            return;
+        }
+        device.poll(wgpu::Maintain::Wait);
+        self.poll(device);
+        self.sender = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.is_recording()
+            && self
+                .encoder_thread
+                .as_ref()
+                .is_none_or(|thread| thread.is_finished())
+    }
+
+    /// Blocks until the file is written. Returns number of written frames
+    pub fn take_result(&mut self) -> Result<u32, String> {
+        let frames = match self.encoder_thread.take() {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|_| Err("encoder thread panicked".to_string()))?,
+            None => return Err("result was already taken".to_string()),
+        };
+        match &self.read_error {
+            Some(err) => Err(format!("reading frames failed after {} frames: {}", frames, err)),
+            None => Ok(frames),
+        }
+    }
+
+    pub fn get_status(&self) -> String {
+        let mut status = format!("{} frames", self.captured_frames);
+        if self.dropped_frames > 0 {
+            status.push_str(&format!(", {} dropped", self.dropped_frames));
+        }
+        status
+    }
+}
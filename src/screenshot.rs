@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+pub const DEFAULT_SCREENSHOT_PATH: &str = "screenshot.png";
+/// Per side, the whole image is kept in memory while it's saved
+pub const MAX_SCREENSHOT_SIZE: u32 = 16384;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotKind {
+    /// Only the cells, at any size, see GoLRenderer::render_image()
+    Simulation,
+    /// Everything in the window including GUI, at the window size
+    Window,
+}
+
+/// Writes RGBA pixels (rows from the top) as PNG
+pub fn save_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = BufWriter::new(File::create(path).map_err(|err| err.to_string())?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(pixels).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())
+}
+
+/// Big images take a while to compress, so it's done on a separate thread. Result is the status message
+pub fn save_png_in_background(path: PathBuf, width: u32, height: u32, pixels: Vec<u8>) -> JoinHandle<String> {
+    std::thread::spawn(move || match save_png(&path, width, height, &pixels) {
+        Ok(()) => format!("Screenshot {}x{} saved to {}", width, height, path.display()),
+        Err(err) => format!("Can't save screenshot: {}", err),
+    })
+}
@@ -0,0 +1,244 @@
+use crate::gol::{pack_cells, unpack_cells};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use glam::Vec2;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+// Session file layout:
+//   magic "GOLSESS\0", u16 major version, u16 minor version (little endian), then zlib stream of chunks.
+//   Chunk is 4 byte tag, u32 length and data. Readers skip chunks with unknown tags, so new data can be added
+//   in a minor version and older builds can still open the file. Major version changes only when existing chunks change meaning.
+//   Minor version 1 added CELS, one byte per cell, written only when some cell has a state other than 0 or 1.
+//   GRID then still has those cells as alive, for builds that don't know CELS
+
+const MAGIC: &[u8; 8] = b"GOLSESS\0";
+const MAJOR_VERSION: u16 = 1;
+const MINOR_VERSION: u16 = 1;
+
+pub const DEFAULT_EXTENSION: &str = "golsession";
+/// The only rule simulation supports, it's still stored so files stay meaningful if more rules are added
+pub const CONWAY_RULE: &str = "B3/S23";
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(std::io::Error),
+    NotASession,
+    /// File was written by a newer, incompatible version
+    UnsupportedVersion(u16, u16),
+    Corrupted(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(err) => write!(f, "{}", err),
+            SessionError::NotASession => write!(f, "file is not a session file"),
+            SessionError::UnsupportedVersion(major, minor) => write!(
+                f,
+                "session version {}.{} is not supported, this build reads version {}.x",
+                major, minor, MAJOR_VERSION
+            ),
+            SessionError::Corrupted(message) => write!(f, "session file is corrupted: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<std::io::Error> for SessionError {
+    fn from(err: std::io::Error) -> Self {
+        SessionError::Io(err)
+    }
+}
+
+/// Everything needed to restore the app to the same state
+pub struct Session {
+    pub grid_width: u32,
+    pub grid_height: u32,
+    /// One byte per cell, same as GoL textures
+    pub cells: Vec<u8>,
+    pub generation: u64,
+    pub rule: String,
+    pub is_paused: bool,
+    pub target_tps: u32,
+    pub camera_position: Vec2,
+    pub camera_zoom: f32,
+    pub camera_rotation: f32,
+}
+
+impl Session {
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&MAJOR_VERSION.to_le_bytes())?;
+        file.write_all(&MINOR_VERSION.to_le_bytes())?;
+
+        let mut encoder = ZlibEncoder::new(file, Compression::default());
+
+        let mut grid = Vec::with_capacity(8 + self.cells.len() / 8 + 1);
+        grid.extend_from_slice(&self.grid_width.to_le_bytes());
+        grid.extend_from_slice(&self.grid_height.to_le_bytes());
+        grid.extend(pack_cells(&self.cells));
+        write_chunk(&mut encoder, b"GRID", &grid)?;
+        if self.cells.iter().any(|cell| *cell > 1) {
+            write_chunk(&mut encoder, b"CELS", &self.cells)?;
+        }
+        write_chunk(&mut encoder, b"GENR", &self.generation.to_le_bytes())?;
+        write_chunk(&mut encoder, b"RULE", self.rule.as_bytes())?;
+
+        let mut config = vec![self.is_paused as u8];
+        config.extend_from_slice(&self.target_tps.to_le_bytes());
+        write_chunk(&mut encoder, b"CONF", &config)?;
+
+        let camera: Vec<u8> = [
+            self.camera_position.x,
+            self.camera_position.y,
+            self.camera_zoom,
+            self.camera_rotation,
+        ]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+        write_chunk(&mut encoder, b"CAMR", &camera)?;
+
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Grid sides above max_grid_side (usually the max texture size of the device) are rejected as corrupted
+    pub fn load(path: &Path, max_grid_side: u32) -> Result<Self, SessionError> {
+        let mut file = std::fs::File::open(path)?;
+        let mut header = [0u8; 12];
+        file.read_exact(&mut header).map_err(|_| SessionError::NotASession)?;
+        if &header[..8] != MAGIC {
+            return Err(SessionError::NotASession);
+        }
+        let major = u16::from_le_bytes([header[8], header[9]]);
+        let minor = u16::from_le_bytes([header[10], header[11]]);
+        if major != MAJOR_VERSION {
+            return Err(SessionError::UnsupportedVersion(major, minor));
+        }
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(file)
+            .read_to_end(&mut data)
+            .map_err(|err| SessionError::Corrupted(err.to_string()))?;
+
+        let mut grid = None;
+        let mut states = None;
+        let mut generation = 0;
+        let mut rule = CONWAY_RULE.to_string();
+        let mut config = (false, 60);
+        let mut camera = (Vec2::ZERO, 1.0, 0.0);
+
+        let mut reader = ChunkReader { data: &data };
+        while let Some((tag, chunk)) = reader.next_chunk()? {
+            let mut chunk = ChunkReader { data: chunk };
+            match tag {
+                b"GRID" => {
+                    let width = chunk.read_u32()?;
+                    let height = chunk.read_u32()?;
+                    if width == 0 || height == 0 || width > max_grid_side || height > max_grid_side {
+                        return Err(SessionError::Corrupted(format!(
+                            "grid size {}x{} is not between 1 and {}",
+                            width, height, max_grid_side
+                        )));
+                    }
+                    let cell_count = width as usize * height as usize;
+                    let packed = chunk.read_bytes(cell_count.div_ceil(8))?;
+                    grid = Some((width, height, unpack_cells(packed, cell_count)));
+                }
+                b"CELS" => states = Some(chunk.data),
+                b"GENR" => generation = chunk.read_u64()?,
+                b"RULE" => {
+                    rule = String::from_utf8(chunk.data.to_vec())
+                        .map_err(|_| SessionError::Corrupted("rule is not valid text".to_string()))?
+                }
+                b"CONF" => config = (chunk.read_bytes(1)?[0] != 0, chunk.read_u32()?.max(1)),
+                b"CAMR" => {
+                    let position = Vec2::new(chunk.read_f32()?, chunk.read_f32()?);
+                    let (zoom, rotation) = (chunk.read_f32()?, chunk.read_f32()?);
+                    if !position.is_finite() || !rotation.is_finite() {
+                        return Err(SessionError::Corrupted("camera position or rotation is not finite".to_string()));
+                    }
+                    if !zoom.is_finite() || zoom <= 0.0 {
+                        return Err(SessionError::Corrupted(format!("camera zoom {} is not positive", zoom)));
+                    }
+                    camera = (position, zoom, rotation);
+                }
+                // written by a newer minor version
+                _ => {}
+            }
+        }
+
+        let (grid_width, grid_height, mut cells) =
+            grid.ok_or_else(|| SessionError::Corrupted("grid is missing".to_string()))?;
+        if let Some(states) = states {
+            if states.len() != cells.len() {
+                return Err(SessionError::Corrupted(format!(
+                    "cell states are for {} cells, grid has {}",
+                    states.len(),
+                    cells.len()
+                )));
+            }
+            cells = states.to_vec();
+        }
+        Ok(Self {
+            grid_width,
+            grid_height,
+            cells,
+            generation,
+            rule,
+            is_paused: config.0,
+            target_tps: config.1,
+            camera_position: camera.0,
+            camera_zoom: camera.1,
+            camera_rotation: camera.2,
+        })
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, tag: &[u8; 4], data: &[u8]) -> Result<(), SessionError> {
+    writer.write_all(tag)?;
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Tag and data
+type Chunk<'a> = (&'a [u8; 4], &'a [u8]);
+
+struct ChunkReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ChunkReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], SessionError> {
+        if self.data.len() < count {
+            return Err(SessionError::Corrupted("unexpected end of data".to_string()));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+    fn read_u32(&mut self) -> Result<u32, SessionError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+    fn read_u64(&mut self) -> Result<u64, SessionError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+    fn read_f32(&mut self) -> Result<f32, SessionError> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+    fn next_chunk(&mut self) -> Result<Option<Chunk<'a>>, SessionError> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.read_bytes(4)?.try_into().unwrap();
+        let length = self.read_u32()? as usize;
+        Ok(Some((tag, self.read_bytes(length)?)))
+    }
+}
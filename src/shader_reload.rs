@@ -0,0 +1,103 @@
+use egui_wgpu::wgpu;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Where the built-in shaders are in the source tree, the app is usually run from the repository root while editing them
+pub const DEFAULT_SIMULATION_SHADER_PATH: &str = "src/gol/shaders.wgsl";
+pub const DEFAULT_RENDER_SHADER_PATH: &str = "src/gol_renderer/shaders.wgsl";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ShaderKind {
+    /// Computes the next generation, see GoL::set_shader()
+    Simulation,
+    /// Draws the cells, see GoLRenderer::set_shader()
+    Render,
+}
+
+impl ShaderKind {
+    pub const ALL: [ShaderKind; 2] = [ShaderKind::Simulation, ShaderKind::Render];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderKind::Simulation => "Simulation",
+            ShaderKind::Render => "Render",
+        }
+    }
+}
+
+/// Validation errors (including WGSL compilation errors) of the pipeline created by `create` are returned
+/// instead of going to the uncaptured error handler, which panics
+pub fn create_checked<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err.to_string()),
+        None => Ok(value),
+    }
+}
+
+struct WatchedFile {
+    kind: ShaderKind,
+    path: PathBuf,
+    /// None until the file is read for the first time
+    modified: Option<SystemTime>,
+    /// Missing file is reported once, not on every poll
+    is_missing: bool,
+}
+
+/// Polls shader files for changes by their modification time, there's no file system notification dependency
+pub struct ShaderWatcher {
+    files: Vec<WatchedFile>,
+    last_poll: Option<Instant>,
+}
+
+impl ShaderWatcher {
+    pub fn new(paths: impl IntoIterator<Item = (ShaderKind, PathBuf)>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|(kind, path)| WatchedFile {
+                    kind,
+                    path,
+                    modified: None,
+                    is_missing: false,
+                })
+                .collect(),
+            last_poll: None,
+        }
+    }
+
+    pub fn get_path(&self, kind: ShaderKind) -> Option<&PathBuf> {
+        self.files.iter().find(|file| file.kind == kind).map(|file| &file.path)
+    }
+
+    /// Sources of shaders changed since the last call, all of them on the first call. Checked at most every POLL_INTERVAL
+    pub fn poll(&mut self) -> Vec<(ShaderKind, Result<String, String>)> {
+        if self.last_poll.is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_poll = Some(Instant::now());
+        let mut changed = Vec::new();
+        for file in &mut self.files {
+            let modified = match std::fs::metadata(&file.path).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                Err(err) => {
+                    if !file.is_missing {
+                        file.is_missing = true;
+                        changed.push((file.kind, Err(format!("can't read {}: {}", file.path.display(), err))));
+                    }
+                    continue;
+                }
+            };
+            if file.modified == Some(modified) && !file.is_missing {
+                continue;
+            }
+            file.modified = Some(modified);
+            file.is_missing = false;
+            let source = std::fs::read_to_string(&file.path).map_err(|err| format!("can't read {}: {}", file.path.display(), err));
+            changed.push((file.kind, source));
+        }
+        changed
+    }
+}
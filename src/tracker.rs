@@ -0,0 +1,253 @@
+use crate::gol::{GoL, PendingRead};
+use crate::gol_manager::patterns::Pattern;
+use egui_wgpu::wgpu;
+use glam::{vec2, Vec2};
+use std::collections::{HashSet, VecDeque};
+
+/// Longest period checked when measuring speed
+const MAX_PERIOD: u64 = 64;
+/// Cells closer than this (in both axes) belong to the same object, spaceships often have 1-2 cell gaps
+const OBJECT_GAP: i64 = 2;
+/// Extra cells read around the object, more is added for generations whose readback is still in flight
+const REGION_MARGIN: u32 = 2 + OBJECT_GAP as u32;
+const MAX_PENDING_READS: usize = 16;
+/// Bigger objects are not tracked, e.g. when the object touches a growing pattern that would swallow the whole grid
+const MAX_OBJECT_CELLS: usize = 16384;
+
+/// Area around the object read after one generation
+struct TrackerRead {
+    generation: u64,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    read: PendingRead,
+}
+
+/// Object in one generation: its bounding box and cells inside it
+struct Observation {
+    generation: u64,
+    x: u32,
+    y: u32,
+    pattern: Pattern,
+}
+
+/// Measured movement of the tracked object
+#[derive(Clone, Copy)]
+struct Speed {
+    period: u64,
+    dx: i64,
+    dy: i64,
+}
+
+impl Speed {
+    /// Usual Life notation, e.g. "c/4 diagonal" for the glider or "c/2 orthogonal" for LWSS
+    fn describe(&self) -> String {
+        if self.dx == 0 && self.dy == 0 {
+            return match self.period {
+                1 => "still life".to_string(),
+                period => format!("p{} oscillator", period),
+            };
+        }
+        let distance = self.dx.unsigned_abs().max(self.dy.unsigned_abs());
+        let divisor = gcd(distance, self.period);
+        let (distance, period) = (distance / divisor, self.period / divisor);
+        let speed = match (distance, period) {
+            (1, 1) => "c".to_string(),
+            (1, period) => format!("c/{}", period),
+            (distance, 1) => format!("{}c", distance),
+            (distance, period) => format!("{}c/{}", distance, period),
+        };
+        let direction = if self.dx == 0 || self.dy == 0 {
+            "orthogonal"
+        } else if self.dx.abs() == self.dy.abs() {
+            "diagonal"
+        } else {
+            "oblique"
+        };
+        format!("{} {} (period {}, moves {}, {})", speed, direction, self.period, self.dx, self.dy)
+    }
+}
+
+/// Follows an object (e.g. a spaceship) across generations. The area around the last known position is read back
+/// after every generation, without waiting for the GPU. The object is the cluster of alive cells connected to the previous one.
+/// Speed is measured by finding the shortest period after which the object has the same shape
+pub struct Tracker {
+    pending_reads: VecDeque<TrackerRead>,
+    /// Newest observation is at the back, older than MAX_PERIOD generations are dropped
+    observations: VecDeque<Observation>,
+    speed: Option<Speed>,
+    is_lost: bool,
+}
+
+impl Tracker {
+    /// Tracks alive cells inside the area (x, y, width, height), usually the selection
+    pub fn start(
+        gol: &GoL,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        (x, y, width, height): (u32, u32, u32, u32),
+    ) -> Result<Self, String> {
+        let cells = pollster::block_on(gol.read_area(device, queue, x, y, width, height));
+        let pattern = Pattern::new(cells, width, height);
+        let Some((content_x, content_y, _, _)) = pattern.content_bounds() else {
+            return Err("selection has no alive cells".to_string());
+        };
+        let mut observations = VecDeque::new();
+        observations.push_back(Observation {
+            generation: gol.get_generation(),
+            x: x + content_x,
+            y: y + content_y,
+            pattern: pattern.cropped_to_content(),
+        });
+        Ok(Self {
+            pending_reads: VecDeque::new(),
+            observations,
+            speed: None,
+            is_lost: false,
+        })
+    }
+
+    /// Starts the readback of the area around the object, has to be called after every GoL::update()
+    pub fn on_generation(&mut self, gol: &GoL, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.is_lost {
+            return;
+        }
+        if self.pending_reads.len() >= MAX_PENDING_READS {
+            device.poll(wgpu::Maintain::Wait);
+            self.poll(device);
+        }
+        let last = self.observations.back().unwrap();
+        let generation = gol.get_generation();
+        // object moves at most one cell per generation, also since the last observation that is not read yet
+        let margin = (REGION_MARGIN as u64 + generation.saturating_sub(last.generation)).min(u32::MAX as u64) as u32;
+        let (x, y) = (last.x.saturating_sub(margin), last.y.saturating_sub(margin));
+        let (gol_width, gol_height) = gol.get_size();
+        let width = (last.x + last.pattern.width + margin).min(gol_width) - x;
+        let height = (last.y + last.pattern.height + margin).min(gol_height) - y;
+        self.pending_reads.push_back(TrackerRead {
+            generation,
+            x,
+            y,
+            width,
+            height,
+            read: gol.start_area_read(device, queue, x, y, width, height),
+        });
+    }
+
+    /// Processes finished readbacks. Should be called every frame
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Poll);
+        while let Some(result) = self.pending_reads.front().and_then(|read| read.read.try_take()) {
+            let read = self.pending_reads.pop_front().unwrap();
+            match result {
+                Ok(data) if !self.is_lost => self.observe(&read, &data),
+                Ok(_) => {}
+                // nothing to measure without the readbacks
+                Err(_) => {
+                    self.is_lost = true;
+                    self.pending_reads.clear();
+                }
+            }
+        }
+    }
+
+    /// Grid cell the object is centered on
+    pub fn get_center(&self) -> Vec2 {
+        let last = self.observations.back().unwrap();
+        vec2(last.x as f32, last.y as f32) + vec2(last.pattern.width as f32, last.pattern.height as f32) / 2.0
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.is_lost
+    }
+
+    pub fn get_status(&self) -> String {
+        if self.is_lost {
+            return "Object lost".to_string();
+        }
+        match &self.speed {
+            Some(speed) => speed.describe(),
+            None => "Measuring speed...".to_string(),
+        }
+    }
+
+    fn observe(&mut self, read: &TrackerRead, cells: &[u8]) {
+        let last = self.observations.back().unwrap();
+        let is_alive = |x: i64, y: i64| {
+            x >= read.x as i64
+                && y >= read.y as i64
+                && x < (read.x + read.width) as i64
+                && y < (read.y + read.height) as i64
+                && cells[(y - read.y as i64) as usize * read.width as usize + (x - read.x as i64) as usize] == 1
+        };
+
+        // cells where the object can be now, then everything close to them
+        let reach = 1 + read.generation.saturating_sub(last.generation).min(MAX_PERIOD) as i64;
+        let mut object = HashSet::new();
+        let mut stack = Vec::new();
+        for y in last.y as i64 - reach..(last.y + last.pattern.height) as i64 + reach {
+            for x in last.x as i64 - reach..(last.x + last.pattern.width) as i64 + reach {
+                if is_alive(x, y) && object.insert((x, y)) {
+                    stack.push((x, y));
+                }
+            }
+        }
+        while let Some((x, y)) = stack.pop() {
+            if object.len() > MAX_OBJECT_CELLS {
+                self.is_lost = true;
+                self.pending_reads.clear();
+                return;
+            }
+            for dy in -OBJECT_GAP..=OBJECT_GAP {
+                for dx in -OBJECT_GAP..=OBJECT_GAP {
+                    if is_alive(x + dx, y + dy) && object.insert((x + dx, y + dy)) {
+                        stack.push((x + dx, y + dy));
+                    }
+                }
+            }
+        }
+        if object.is_empty() {
+            self.is_lost = true;
+            return;
+        }
+
+        let cells: Vec<(i64, i64)> = object.into_iter().collect();
+        let x = cells.iter().map(|(x, _)| *x).min().unwrap() as u32;
+        let y = cells.iter().map(|(_, y)| *y).min().unwrap() as u32;
+        let observation = Observation {
+            generation: read.generation,
+            x,
+            y,
+            pattern: Pattern::from_alive_cells(&cells),
+        };
+        self.speed = self.find_speed(&observation).or(self.speed);
+        self.observations.push_back(observation);
+        while self.observations.front().is_some_and(|first| first.generation + MAX_PERIOD < read.generation) {
+            self.observations.pop_front();
+        }
+    }
+
+    /// Shortest period after which the object has the same shape, and how far it moved in that time
+    fn find_speed(&self, observation: &Observation) -> Option<Speed> {
+        self.observations
+            .iter()
+            .rev()
+            .filter(|old| {
+                old.generation < observation.generation
+                    && old.pattern.width == observation.pattern.width
+                    && old.pattern.height == observation.pattern.height
+                    && old.pattern.data == observation.pattern.data
+            })
+            .map(|old| Speed {
+                period: observation.generation - old.generation,
+                dx: observation.x as i64 - old.x as i64,
+                dy: observation.y as i64 - old.y as i64,
+            })
+            .next()
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}